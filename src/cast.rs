@@ -0,0 +1,256 @@
+use anyhow::{Context, Result, anyhow, bail};
+use clap::ValueEnum;
+use serde_json::{Map, Number, Value};
+
+use crate::datetime::{TimeInput, parse_time};
+use crate::number;
+
+/// Type a column is converted to.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CastType {
+    /// Whole number, e.g. `42`; `"42"` and `42.0` are accepted
+    Int,
+    /// Floating point number
+    Float,
+    /// Exact decimal number written without an exponent
+    Decimal,
+    /// `true`/`false`; also accepts `1`/`0`, `yes`/`no`, `t`/`f`, `y`/`n`
+    Bool,
+    /// Text; numbers and booleans are kept as written, objects and arrays become JSON
+    String,
+    /// Calendar date `YYYY-MM-DD` in the timestamp's own offset, from an ISO-8601,
+    /// RFC 2822 or epoch-seconds timestamp
+    Date,
+    /// JSON text; strings must hold a JSON document
+    Json,
+}
+
+impl CastType {
+    fn name(self) -> &'static str {
+        match self {
+            CastType::Int => "int",
+            CastType::Float => "float",
+            CastType::Decimal => "decimal",
+            CastType::Bool => "bool",
+            CastType::String => "string",
+            CastType::Date => "date",
+            CastType::Json => "json",
+        }
+    }
+}
+
+/// What to do with a value that cannot be cast.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CastFailure {
+    /// Reject the record: abort, or skip it with `--skip-invalid`
+    Error,
+    /// Write the value as null
+    Null,
+    /// Always skip the record and write it to the `--rejects` file
+    Reject,
+}
+
+/// A column and the type its values must have: `COLUMN:TYPE`.
+#[derive(Debug, Clone)]
+pub struct Cast {
+    pub column: String,
+    pub to: CastType,
+}
+
+impl Cast {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (column, to) = spec
+            .rsplit_once(':')
+            .with_context(|| format!("Invalid cast \"{}\", expected COLUMN:TYPE", spec))?;
+        let to = CastType::from_str(to, true).map_err(|_| anyhow!("Invalid cast \"{}\": unknown type \"{}\"", spec, to))?;
+        Ok(Self { column: column.to_string(), to })
+    }
+}
+
+/// Converts columns to their declared types. Nulls and missing columns are left alone.
+pub struct Caster {
+    pub casts: Vec<Cast>,
+    pub on_error: CastFailure,
+}
+
+impl Caster {
+    pub fn cast(&self, row: &mut Map<String, Value>) -> Result<()> {
+        for cast in &self.casts {
+            let Some(v) = row.get_mut(&cast.column) else { continue };
+            if v.is_null() {
+                continue;
+            }
+            match cast_value(v, cast.to) {
+                Ok(cast) => *v = cast,
+                Err(_) if self.on_error == CastFailure::Null => *v = Value::Null,
+                Err(err) => {
+                    return Err(err.context(format!("Cannot cast {} in column \"{}\" to {}", v, cast.column, cast.to.name())));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn cast_value(v: &Value, to: CastType) -> Result<Value> {
+    Ok(match to {
+        CastType::Int => Value::Number(parse_number(v)?.and_then(|text| integer(&text)).context("not an integer")?),
+        CastType::Float => {
+            let text = parse_number(v)?.context("not a number")?;
+            let x: f64 = text.parse()?;
+            Value::Number(Number::from_f64(x).context("number out of range")?)
+        }
+        CastType::Decimal => {
+            let text = parse_number(v)?.context("not a number")?;
+            Value::Number(number::plain(&text).parse()?)
+        }
+        CastType::Bool => Value::Bool(match v {
+            Value::Bool(b) => *b,
+            Value::Number(n) if n.to_string() == "1" => true,
+            Value::Number(n) if n.to_string() == "0" => false,
+            Value::String(s) => match s.trim().to_ascii_lowercase().as_str() {
+                "true" | "t" | "yes" | "y" | "1" => true,
+                "false" | "f" | "no" | "n" | "0" => false,
+                _ => bail!("not a boolean"),
+            },
+            _ => bail!("not a boolean"),
+        }),
+        CastType::String => Value::String(match v {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        }),
+        CastType::Date => {
+            let time = parse_time(v, TimeInput::Auto)?;
+            Value::String(time.format("%Y-%m-%d").to_string())
+        }
+        CastType::Json => Value::String(match v {
+            Value::String(s) => serde_json::from_str::<Value>(s).context("not a JSON document")?.to_string(),
+            other => other.to_string(),
+        }),
+    })
+}
+
+/// Text of a number, or of a string holding one. `None` for other values.
+/// Exponents too large to write the number out in digits are refused.
+fn parse_number(v: &Value) -> Result<Option<String>> {
+    let text = match v {
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.trim().parse::<Number>().ok().context("not a number")?.to_string(),
+        _ => return Ok(None),
+    };
+    if let Some(i) = text.find(['e', 'E'])
+        && text[i + 1..].parse::<i64>().map_or(true, |exp| exp.abs() > number::MAX_EXPONENT)
+    {
+        bail!("exponent too large");
+    }
+    Ok(Some(text))
+}
+
+/// The number as an integer, when it has no fractional part.
+fn integer(text: &str) -> Option<Number> {
+    let plain = number::plain(text);
+    let int = match plain.split_once('.') {
+        Some((int, frac)) if frac.bytes().all(|b| b == b'0') => int,
+        Some(_) => return None,
+        None => &plain,
+    };
+    int.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn cast(v: Value, to: CastType) -> Result<Value> {
+        cast_value(&v, to)
+    }
+
+    fn number(text: &str) -> Value {
+        serde_json::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn casts_to_int() {
+        assert_eq!(cast(json!("42"), CastType::Int).unwrap(), number("42"));
+        assert_eq!(cast(json!(" -7 "), CastType::Int).unwrap(), number("-7"));
+        assert_eq!(cast(number("42.0"), CastType::Int).unwrap(), number("42"));
+        assert_eq!(cast(number("1.5e3"), CastType::Int).unwrap(), number("1500"));
+        assert_eq!(cast(number("123456789012345678901234567890"), CastType::Int).unwrap().to_string(), "123456789012345678901234567890");
+        for v in [json!("4.5"), number("4.5"), json!("abc"), json!(true), json!([1])] {
+            assert!(cast(v, CastType::Int).is_err());
+        }
+    }
+
+    #[test]
+    fn casts_to_float_and_decimal() {
+        assert_eq!(cast(json!("0.5"), CastType::Float).unwrap(), json!(0.5));
+        assert_eq!(cast(number("3"), CastType::Float).unwrap(), json!(3.0));
+        let err = cast(number("1e400"), CastType::Float).unwrap_err();
+        assert_eq!(err.to_string(), "number out of range");
+        assert_eq!(cast(json!("1.25e2"), CastType::Decimal).unwrap().to_string(), "125");
+        assert_eq!(cast(number("2.5E-3"), CastType::Decimal).unwrap().to_string(), "0.0025");
+        assert_eq!(cast(number("0.1000000000000000055511"), CastType::Decimal).unwrap().to_string(), "0.1000000000000000055511");
+        assert!(cast(json!("1,5"), CastType::Decimal).is_err());
+    }
+
+    #[test]
+    fn refuses_huge_exponents() {
+        let limit = format!("1e{}", number::MAX_EXPONENT + 1);
+        for to in [CastType::Int, CastType::Decimal, CastType::Float] {
+            assert_eq!(cast(json!(limit), to).unwrap_err().to_string(), "exponent too large");
+            assert_eq!(cast(json!("1e999999999999999999999"), to).unwrap_err().to_string(), "exponent too large");
+        }
+        assert!(cast(json!(format!("1e{}", number::MAX_EXPONENT)), CastType::Int).is_ok());
+    }
+
+    #[test]
+    fn casts_to_bool() {
+        for text in ["true", "T", "yes", "Y", "1", " TRUE "] {
+            assert_eq!(cast(json!(text), CastType::Bool).unwrap(), json!(true), "{}", text);
+        }
+        for text in ["false", "f", "No", "n", "0"] {
+            assert_eq!(cast(json!(text), CastType::Bool).unwrap(), json!(false), "{}", text);
+        }
+        assert_eq!(cast(json!(1), CastType::Bool).unwrap(), json!(true));
+        assert_eq!(cast(json!(0), CastType::Bool).unwrap(), json!(false));
+        for v in [json!("maybe"), json!(2), number("1.0"), json!({})] {
+            assert!(cast(v, CastType::Bool).is_err());
+        }
+    }
+
+    #[test]
+    fn casts_to_text() {
+        assert_eq!(cast(number("12.0"), CastType::String).unwrap(), json!("12.0"));
+        assert_eq!(cast(json!({"a": 1}), CastType::String).unwrap(), json!("{\"a\":1}"));
+        assert_eq!(cast(json!("{\"a\": [1, 2]}"), CastType::Json).unwrap(), json!("{\"a\":[1,2]}"));
+        assert_eq!(cast(json!([1, 2]), CastType::Json).unwrap(), json!("[1,2]"));
+        assert!(cast(json!("{bad"), CastType::Json).is_err());
+    }
+
+    #[test]
+    fn casts_to_date() {
+        assert_eq!(cast(json!("2024-03-01T23:30:00-05:00"), CastType::Date).unwrap(), json!("2024-03-01"));
+        assert_eq!(cast(json!(1700000000), CastType::Date).unwrap(), json!("2023-11-14"));
+        assert!(cast(json!("yesterday"), CastType::Date).is_err());
+    }
+
+    #[test]
+    fn parses_cast_specs() {
+        let c = Cast::parse("a:b:INT").unwrap();
+        assert_eq!((c.column.as_str(), c.to), ("a:b", CastType::Int));
+        assert!(Cast::parse("amount").is_err());
+        assert!(Cast::parse("amount:money").is_err());
+    }
+
+    #[test]
+    fn applies_the_failure_policy() {
+        let mut row = json!({"id": "x", "n": null}).as_object().unwrap().clone();
+        let casts = vec![Cast::parse("id:int").unwrap(), Cast::parse("n:int").unwrap()];
+        let error = Caster { casts: casts.clone(), on_error: CastFailure::Error };
+        assert!(error.cast(&mut row.clone()).is_err());
+        let null = Caster { casts, on_error: CastFailure::Null };
+        null.cast(&mut row).unwrap();
+        assert_eq!(Value::Object(row), json!({"id": null, "n": null}));
+    }
+}
@@ -0,0 +1,47 @@
+use std::io::{BufRead, BufReader};
+use anyhow::Result;
+use clap::ValueEnum;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    /// Detect from the leading magic bytes
+    Auto,
+    None,
+    Gzip,
+    Zstd,
+    Bzip2,
+    Xz,
+}
+
+impl Compression {
+    /// Recognize the format from the first bytes of a stream.
+    fn sniff(head: &[u8]) -> Self {
+        if head.starts_with(&[0x1f, 0x8b]) {
+            Compression::Gzip
+        } else if head.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Compression::Zstd
+        } else if head.starts_with(b"BZh") {
+            Compression::Bzip2
+        } else if head.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Compression::Xz
+        } else {
+            Compression::None
+        }
+    }
+}
+
+/// Wrap `reader` with the decoder for `compression`, sniffing the format when it is `Auto`.
+pub fn decode(mut reader: Box<dyn BufRead>, compression: Compression) -> Result<Box<dyn BufRead>> {
+    let compression = match compression {
+        Compression::Auto => Compression::sniff(reader.fill_buf()?),
+        other => other,
+    };
+    Ok(match compression {
+        Compression::Auto | Compression::None => reader,
+        // Multi-member readers, so concatenated archives (`cat a.gz b.gz`) are read to the end
+        Compression::Gzip => Box::new(BufReader::new(flate2::bufread::MultiGzDecoder::new(reader))),
+        Compression::Zstd => Box::new(BufReader::new(zstd::Decoder::with_buffer(reader)?)),
+        Compression::Bzip2 => Box::new(BufReader::new(bzip2::bufread::MultiBzDecoder::new(reader))),
+        Compression::Xz => Box::new(BufReader::new(xz2::bufread::XzDecoder::new_multi_decoder(reader))),
+    })
+}
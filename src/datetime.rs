@@ -0,0 +1,249 @@
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime};
+use chrono_tz::Tz;
+use serde_json::{Map, Value};
+
+use crate::number;
+
+/// How the values of a time column are written in the input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeInput {
+    /// ISO-8601 or RFC 2822 strings, numbers as epoch seconds
+    Auto,
+    Iso8601,
+    Rfc2822,
+    EpochSeconds,
+    EpochMillis,
+    EpochMicros,
+    EpochNanos,
+}
+
+impl TimeInput {
+    fn parse(kind: &str) -> Option<Self> {
+        Some(match kind {
+            "auto" => TimeInput::Auto,
+            "iso8601" => TimeInput::Iso8601,
+            "rfc2822" => TimeInput::Rfc2822,
+            "epoch-s" => TimeInput::EpochSeconds,
+            "epoch-ms" => TimeInput::EpochMillis,
+            "epoch-us" => TimeInput::EpochMicros,
+            "epoch-ns" => TimeInput::EpochNanos,
+            _ => return None,
+        })
+    }
+}
+
+/// A column holding timestamps: `COLUMN` or `COLUMN:KIND`.
+#[derive(Debug, Clone)]
+pub struct TimeColumn {
+    pub name: String,
+    pub input: TimeInput,
+}
+
+impl TimeColumn {
+    pub fn parse(spec: &str) -> Result<Self> {
+        // Column names may contain `:` themselves, so only a known kind is split off
+        if let Some((name, kind)) = spec.rsplit_once(':')
+            && let Some(input) = TimeInput::parse(kind)
+        {
+            return Ok(Self { name: name.to_string(), input });
+        }
+        if spec.is_empty() {
+            bail!("Invalid time column \"{}\", expected COLUMN[:KIND]", spec);
+        }
+        Ok(Self { name: spec.to_string(), input: TimeInput::Auto })
+    }
+}
+
+/// Rewrites timestamp columns in a chosen format and timezone.
+pub struct TimeConverter {
+    pub columns: Vec<TimeColumn>,
+    /// strftime format of the output
+    pub format: String,
+    pub timezone: Tz,
+}
+
+impl TimeConverter {
+    pub fn new(columns: Vec<TimeColumn>, format: String, timezone: &str) -> Result<Self> {
+        if StrftimeItems::new(&format).any(|item| item == Item::Error) {
+            bail!("Invalid time format \"{}\"", format);
+        }
+        let timezone = timezone
+            .parse()
+            .map_err(|_| anyhow!("Unknown timezone \"{}\"", timezone))?;
+        Ok(Self { columns, format, timezone })
+    }
+
+    /// Convert the time columns of a row in place. Nulls and missing columns are left alone.
+    pub fn convert(&self, row: &mut Map<String, Value>) -> Result<()> {
+        for col in &self.columns {
+            let Some(v) = row.get_mut(&col.name) else { continue };
+            if v.is_null() {
+                continue;
+            }
+            let time = parse_time(v, col.input)
+                .with_context(|| format!("Invalid timestamp {} in column \"{}\"", v, col.name))?;
+            *v = Value::String(time.with_timezone(&self.timezone).format(&self.format).to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Read a string or number timestamp written as `input`, keeping the offset it was written with.
+pub fn parse_time(v: &Value, input: TimeInput) -> Result<DateTime<FixedOffset>> {
+    let text = match v {
+        Value::String(s) => s.trim().to_string(),
+        // Epochs like `1.7e9` are read as plain digits
+        Value::Number(n) => number::plain(&n.to_string()),
+        _ => bail!("expected a string or a number"),
+    };
+    match input {
+        // Only JSON numbers are epochs here, a string like "2024" is not taken for one
+        TimeInput::Auto if v.is_number() => parse_epoch(&text, TimeInput::EpochSeconds),
+        TimeInput::Auto => parse_iso8601(&text)
+            .or_else(|_| parse_rfc2822(&text))
+            .map_err(|_| anyhow!("not an ISO-8601 or RFC 2822 timestamp")),
+        TimeInput::Iso8601 => parse_iso8601(&text),
+        TimeInput::Rfc2822 => parse_rfc2822(&text),
+        _ => parse_epoch(&text, input),
+    }
+}
+
+/// Timestamps without an offset are taken as UTC; a bare date is midnight.
+fn parse_iso8601(text: &str) -> Result<DateTime<FixedOffset>> {
+    if let Ok(t) = DateTime::parse_from_rfc3339(text) {
+        return Ok(t);
+    }
+    for format in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M"] {
+        if let Ok(t) = NaiveDateTime::parse_from_str(text, format) {
+            return Ok(t.and_utc().fixed_offset());
+        }
+    }
+    let date = NaiveDate::parse_from_str(text, "%Y-%m-%d").context("not an ISO-8601 timestamp")?;
+    Ok(date.and_time(Default::default()).and_utc().fixed_offset())
+}
+
+fn parse_rfc2822(text: &str) -> Result<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc2822(text).context("not an RFC 2822 timestamp")
+}
+
+/// An epoch timestamp in `unit`: digits with an optional sign and fraction, e.g. `1700000000.25`.
+/// The fraction is read exactly, digits finer than a nanosecond are dropped.
+fn parse_epoch(text: &str, unit: TimeInput) -> Result<DateTime<FixedOffset>> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let (int, frac) = digits.split_once('.').unwrap_or((digits, ""));
+    if int.is_empty() || !int.bytes().all(|b| b.is_ascii_digit()) || !frac.bytes().all(|b| b.is_ascii_digit()) {
+        bail!("not an epoch timestamp");
+    }
+    // Digits the unit adds to whole seconds, e.g. 3 for milliseconds
+    let scale = match unit {
+        TimeInput::EpochMillis => 3,
+        TimeInput::EpochMicros => 6,
+        TimeInput::EpochNanos => 9,
+        _ => 0,
+    };
+    // Fraction digits that still count whole nanoseconds
+    let shift = 9 - scale;
+    let frac: String = frac.chars().chain(std::iter::repeat('0')).take(shift).collect();
+    let nanos = int
+        .parse::<i128>()
+        .ok()
+        .and_then(|n| n.checked_mul(10_i128.pow(shift as u32)))
+        .and_then(|n| n.checked_add(frac.parse().unwrap_or(0)))
+        .context("epoch timestamp out of range")?;
+    let nanos = if negative { -nanos } else { nanos };
+    let secs = i64::try_from(nanos.div_euclid(1_000_000_000)).context("epoch timestamp out of range")?;
+    let time = DateTime::from_timestamp(secs, nanos.rem_euclid(1_000_000_000) as u32);
+    Ok(time.context("epoch timestamp out of range")?.fixed_offset())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parse(v: Value, input: TimeInput) -> Result<String> {
+        Ok(parse_time(&v, input)?.to_utc().to_rfc3339())
+    }
+
+    #[test]
+    fn keeps_the_offset_of_the_input() {
+        let t = parse_time(&json!("2024-03-01T23:30:00-05:00"), TimeInput::Auto).unwrap();
+        assert_eq!(t.to_rfc3339(), "2024-03-01T23:30:00-05:00");
+    }
+
+    #[test]
+    fn parses_iso8601() {
+        let cases = [
+            ("2024-03-01T12:00:00Z", "2024-03-01T12:00:00+00:00"),
+            ("2024-03-01T12:00:00.250+02:00", "2024-03-01T10:00:00.250+00:00"),
+            ("2024-03-01T23:30:00-05:00", "2024-03-02T04:30:00+00:00"),
+            ("2024-03-01 08:30:00", "2024-03-01T08:30:00+00:00"),
+            ("2024-03-01T08:30", "2024-03-01T08:30:00+00:00"),
+            ("2024-03-01", "2024-03-01T00:00:00+00:00"),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(parse(json!(text), TimeInput::Iso8601).unwrap(), expected, "{}", text);
+            assert_eq!(parse(json!(text), TimeInput::Auto).unwrap(), expected, "{}", text);
+        }
+    }
+
+    #[test]
+    fn parses_rfc2822() {
+        let text = "Tue, 1 Jul 2003 10:52:37 +0200";
+        assert_eq!(parse(json!(text), TimeInput::Rfc2822).unwrap(), "2003-07-01T08:52:37+00:00");
+        assert_eq!(parse(json!(text), TimeInput::Auto).unwrap(), "2003-07-01T08:52:37+00:00");
+        assert!(parse(json!("2024-03-01T12:00:00Z"), TimeInput::Rfc2822).is_err());
+    }
+
+    #[test]
+    fn parses_epochs_in_every_unit() {
+        let cases = [
+            (json!(1700000000), TimeInput::EpochSeconds),
+            (json!("1700000000"), TimeInput::EpochSeconds),
+            (json!(1700000000123_i64), TimeInput::EpochMillis),
+            (json!(1700000000123456_i64), TimeInput::EpochMicros),
+            (json!(1700000000123456789_i64), TimeInput::EpochNanos),
+            (json!(1700000000), TimeInput::Auto),
+        ];
+        let expected = [
+            "2023-11-14T22:13:20+00:00",
+            "2023-11-14T22:13:20+00:00",
+            "2023-11-14T22:13:20.123+00:00",
+            "2023-11-14T22:13:20.123456+00:00",
+            "2023-11-14T22:13:20.123456789+00:00",
+            "2023-11-14T22:13:20+00:00",
+        ];
+        for ((v, input), expected) in cases.into_iter().zip(expected) {
+            assert_eq!(parse(v, input).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn parses_fractional_epochs_exactly() {
+        let v: Value = serde_json::from_str("1700000000.123").unwrap();
+        assert_eq!(parse(v, TimeInput::EpochSeconds).unwrap(), "2023-11-14T22:13:20.123+00:00");
+        assert_eq!(parse(json!("1700000000.1"), TimeInput::EpochSeconds).unwrap(), "2023-11-14T22:13:20.100+00:00");
+        assert_eq!(parse(json!("1700000000123.5"), TimeInput::EpochMillis).unwrap(), "2023-11-14T22:13:20.123500+00:00");
+        assert_eq!(parse(json!("-1.5"), TimeInput::EpochSeconds).unwrap(), "1969-12-31T23:59:58.500+00:00");
+        let v: Value = serde_json::from_str("1.7e9").unwrap();
+        assert_eq!(parse(v, TimeInput::EpochSeconds).unwrap(), "2023-11-14T22:13:20+00:00");
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        for text in ["NaN", "-nan", "inf", "1e3", "1.2.3", "", ".5", "12a", "99999999999999999999999999999999999999999"] {
+            assert!(parse(json!(text), TimeInput::EpochSeconds).is_err(), "{}", text);
+        }
+        assert!(parse(json!("2024"), TimeInput::Auto).is_err());
+        assert!(parse(json!("not a date"), TimeInput::Auto).is_err());
+        assert!(parse(json!("2024-13-01"), TimeInput::Iso8601).is_err());
+        assert!(parse(json!(true), TimeInput::Auto).is_err());
+        let v: Value = serde_json::from_str("1e99999999").unwrap();
+        assert!(parse(v, TimeInput::Auto).is_err());
+    }
+}
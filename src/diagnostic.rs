@@ -0,0 +1,160 @@
+use std::fmt;
+use std::str::Utf8Error;
+use serde_json::error::Category;
+
+/// Bytes of context shown before and after the error position.
+const EXCERPT_BEFORE: usize = 40;
+const EXCERPT_AFTER: usize = 30;
+
+/// A line that is not valid JSON, located down to the column, with an excerpt pointing at it.
+#[derive(Debug)]
+pub struct ParseError {
+    pub category: &'static str,
+    pub message: String,
+    /// 1-based byte column of the error within the line
+    pub column: usize,
+    excerpt: String,
+    caret: usize,
+}
+
+impl ParseError {
+    pub fn json(err: &serde_json::Error, line: &str) -> Self {
+        let category = match err.classify() {
+            Category::Io => "I/O",
+            Category::Syntax => "syntax",
+            Category::Data => "data",
+            Category::Eof => "EOF",
+        };
+        // serde_json appends the position, which is reported separately
+        let message = err.to_string();
+        let message = match message.rfind(" at line ") {
+            Some(end) => message[..end].to_string(),
+            None => message,
+        };
+        Self::new(category, message, err.column().max(1), line)
+    }
+
+    pub fn utf8(err: &Utf8Error, line: &str) -> Self {
+        Self::new("encoding", "invalid UTF-8".to_string(), err.valid_up_to() + 1, line)
+    }
+
+    /// The error without the excerpt, on one line.
+    pub fn summary(&self) -> String {
+        format!("{} error: {}", self.category, self.message)
+    }
+
+    fn new(category: &'static str, message: String, column: usize, line: &str) -> Self {
+        let (excerpt, caret) = excerpt(line, column - 1);
+        Self {
+            category,
+            message,
+            column,
+            excerpt,
+            caret,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\n  {}\n  {:>width$}", self.summary(), self.excerpt, "^", width = self.caret + 1)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// A window of `line` around byte `index`, and the char position of `index` within it.
+fn excerpt(line: &str, index: usize) -> (String, usize) {
+    let mut index = index.min(line.len());
+    while !line.is_char_boundary(index) {
+        index -= 1;
+    }
+    let mut start = index.saturating_sub(EXCERPT_BEFORE);
+    while !line.is_char_boundary(start) {
+        start -= 1;
+    }
+    let mut end = (index + EXCERPT_AFTER).min(line.len());
+    while !line.is_char_boundary(end) {
+        end += 1;
+    }
+
+    let mut text = String::new();
+    if start > 0 {
+        text.push_str("...");
+    }
+    let caret = text.len() + line[start..index].chars().count();
+    // Keep the excerpt on one line and the caret aligned
+    text.extend(line[start..end].chars().map(|c| if c.is_control() { ' ' } else { c }));
+    if end < line.len() {
+        text.push_str("...");
+    }
+    (text, caret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_error(line: &str) -> ParseError {
+        let err = serde_json::from_str::<serde_json::Value>(line).unwrap_err();
+        ParseError::json(&err, line)
+    }
+
+    /// The excerpt with the caret line under it, as printed.
+    fn pointed(err: &ParseError) -> String {
+        format!("{}\n{:>width$}", err.excerpt, "^", width = err.caret + 1)
+    }
+
+    #[test]
+    fn points_after_multibyte_characters() {
+        let err = parse_error(r#"{"é":x}"#);
+        assert_eq!(err.column, 7);
+        assert_eq!(pointed(&err), "{\"é\":x}\n     ^");
+    }
+
+    #[test]
+    fn points_at_column_one() {
+        let err = parse_error("x");
+        assert_eq!(err.column, 1);
+        assert_eq!((err.excerpt.as_str(), err.caret), ("x", 0));
+        let (text, caret) = excerpt("]abc", 0);
+        assert_eq!((text.as_str(), caret), ("]abc", 0));
+    }
+
+    #[test]
+    fn elides_both_sides_of_long_lines() {
+        let line = format!("{}X{}", "a".repeat(60), "b".repeat(60));
+        let (text, caret) = excerpt(&line, 60);
+        assert_eq!(text, format!("...{}X{}...", "a".repeat(EXCERPT_BEFORE), "b".repeat(EXCERPT_AFTER - 1)));
+        assert_eq!(text.chars().nth(caret), Some('X'));
+    }
+
+    #[test]
+    fn keeps_char_boundaries_at_the_window_edges() {
+        let line = format!("{}X{}", "é".repeat(30), "ü".repeat(30));
+        // Index of X, with the window edges falling inside two-byte characters
+        let (text, caret) = excerpt(&line, 60);
+        assert!(text.starts_with("...é") && text.ends_with("ü..."));
+        assert_eq!(text.chars().nth(caret), Some('X'));
+        // An index inside a character moves back to its start
+        let (_, caret) = excerpt("aé", 2);
+        assert_eq!(caret, 1);
+    }
+
+    #[test]
+    fn blanks_control_characters() {
+        let (text, caret) = excerpt("a\tb\u{1}c", 4);
+        assert_eq!(text, "a b c");
+        assert_eq!(caret, 4);
+    }
+
+    #[test]
+    fn reports_invalid_utf8() {
+        let bytes = b"ab\xffcd".to_vec();
+        let e = std::str::from_utf8(&bytes).unwrap_err();
+        let err = ParseError::utf8(&e, &String::from_utf8_lossy(&bytes));
+        assert_eq!(err.column, 3);
+        assert_eq!(err.summary(), "encoding error: invalid UTF-8");
+        assert_eq!(pointed(&err), "ab\u{fffd}cd\n  ^");
+    }
+}
@@ -0,0 +1,104 @@
+use clap::ValueEnum;
+use csv::{QuoteStyle, Terminator, WriterBuilder};
+
+/// Named sets of dialect options.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Preset {
+    /// Comma separated, CRLF line endings, quotes only where needed
+    Excel,
+    /// Tab separated, LF line endings
+    Tsv,
+    /// Comma separated, CRLF line endings, quotes doubled inside quoted fields
+    Rfc4180,
+    /// Comma separated, LF line endings, every field quoted
+    Unix,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Quoting {
+    /// Quote every field
+    Always,
+    /// Quote fields holding a delimiter, quote or line break
+    Necessary,
+    /// Quote every field that does not look like a number
+    NonNumeric,
+    /// Never quote; fields holding special characters are written as is
+    Never,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+}
+
+/// Formatting of the CSV output.
+pub struct Dialect {
+    pub delimiter: u8,
+    pub quote: u8,
+    /// Escape quotes with this character instead of doubling them
+    pub escape: Option<u8>,
+    pub quoting: Quoting,
+    pub line_ending: LineEnding,
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            quote: b'"',
+            escape: None,
+            quoting: Quoting::Necessary,
+            line_ending: LineEnding::Lf,
+        }
+    }
+}
+
+impl Dialect {
+    pub fn preset(preset: Preset) -> Self {
+        match preset {
+            Preset::Excel | Preset::Rfc4180 => Self {
+                line_ending: LineEnding::Crlf,
+                ..Self::default()
+            },
+            Preset::Tsv => Self {
+                delimiter: b'\t',
+                ..Self::default()
+            },
+            Preset::Unix => Self {
+                quoting: Quoting::Always,
+                ..Self::default()
+            },
+        }
+    }
+
+    pub fn builder(&self) -> WriterBuilder {
+        let mut builder = WriterBuilder::new();
+        builder
+            .delimiter(self.delimiter)
+            .quote(self.quote)
+            .quote_style(match self.quoting {
+                Quoting::Always => QuoteStyle::Always,
+                Quoting::Necessary => QuoteStyle::Necessary,
+                Quoting::NonNumeric => QuoteStyle::NonNumeric,
+                Quoting::Never => QuoteStyle::Never,
+            })
+            .terminator(match self.line_ending {
+                LineEnding::Lf => Terminator::Any(b'\n'),
+                LineEnding::Crlf => Terminator::CRLF,
+            });
+        if let Some(escape) = self.escape {
+            builder.double_quote(false).escape(escape);
+        }
+        builder
+    }
+}
+
+/// Parse a single-byte dialect character; `\t` and `tab` stand for a tab.
+pub fn parse_char(s: &str) -> Result<u8, String> {
+    match s {
+        "\\t" | "tab" => Ok(b'\t'),
+        _ if s.len() == 1 && s.is_ascii() => Ok(s.as_bytes()[0]),
+        _ => Err(format!("expected a single ASCII character, got \"{}\"", s)),
+    }
+}
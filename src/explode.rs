@@ -0,0 +1,74 @@
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+
+/// What to do with records whose exploded array is empty, null or missing.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmptyArray {
+    /// Keep one row, with the exploded field left out
+    Keep,
+    /// Emit no row for the record
+    Drop,
+}
+
+/// Emits one row per element of an array field, repeating the other fields (like `$unwind`).
+pub struct Exploder {
+    pub field: String,
+    /// Column receiving the 0-based position of the element in the array
+    pub index_column: Option<String>,
+    pub empty: EmptyArray,
+}
+
+impl Exploder {
+    pub fn explode(&self, obj: Map<String, Value>) -> Vec<Map<String, Value>> {
+        let elements = match obj.get(&self.field) {
+            Some(Value::Array(items)) => items.clone(),
+            None | Some(Value::Null) => Vec::new(),
+            // A scalar or object is treated as a single-element array
+            Some(other) => vec![other.clone()],
+        };
+
+        if elements.is_empty() {
+            return match self.empty {
+                EmptyArray::Keep => vec![self.with_element(&obj, None, None)],
+                EmptyArray::Drop => Vec::new(),
+            };
+        }
+
+        elements
+            .into_iter()
+            .enumerate()
+            .map(|(i, element)| self.with_element(&obj, Some(i), Some(element)))
+            .collect()
+    }
+
+    /// Copy of `obj` with the array replaced by one element, the index column placed just before it.
+    fn with_element(&self, obj: &Map<String, Value>, index: Option<usize>, element: Option<Value>) -> Map<String, Value> {
+        let mut row = Map::new();
+        let mut element = element;
+        let mut placed = false;
+        for (k, v) in obj {
+            if *k == self.field {
+                self.insert_element(&mut row, index, element.take());
+                placed = true;
+            } else {
+                row.insert(k.clone(), v.clone());
+            }
+        }
+        // The field was missing from the record
+        if !placed {
+            self.insert_element(&mut row, index, element);
+        }
+        row
+    }
+
+    /// Without an element (the keep-row of an empty array) the field is left out, so it
+    /// shows as missing rather than as a `null` leaf that `--flatten` would make a column of.
+    fn insert_element(&self, row: &mut Map<String, Value>, index: Option<usize>, element: Option<Value>) {
+        if let Some(col) = &self.index_column {
+            row.insert(col.clone(), index.map_or(Value::Null, Value::from));
+        }
+        if let Some(element) = element {
+            row.insert(self.field.clone(), element);
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+
+/// What to do with arrays longer than `--max-array-items`.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArrayOverflow {
+    /// Drop the items past the limit
+    Truncate,
+    /// Write the items past the limit as one JSON array in a `<path>.rest` column
+    Stringify,
+    /// Abort the conversion
+    Error,
+}
+
+/// Expands nested objects and arrays into columns named after their path,
+/// e.g. `user.address.city` or `tags.0`.
+pub struct Flattener {
+    pub separator: String,
+    pub objects: bool,
+    pub arrays: bool,
+    /// Levels of nesting to expand; deeper values are kept whole and rendered as JSON
+    pub max_depth: Option<usize>,
+    pub max_array_items: Option<usize>,
+    pub overflow: ArrayOverflow,
+}
+
+impl Flattener {
+    /// Flatten one record. Keys keep the order of the source object, nested keys are
+    /// inserted where their parent was, so the column order is stable across records.
+    pub fn flatten(&self, obj: Map<String, Value>) -> Result<Map<String, Value>> {
+        let mut out = Map::new();
+        for (k, v) in obj {
+            self.flatten_into(&mut out, k, v, 1)?;
+        }
+        Ok(out)
+    }
+
+    fn flatten_into(&self, out: &mut Map<String, Value>, path: String, v: Value, depth: usize) -> Result<()> {
+        let expand = self.max_depth.is_none_or(|max| depth <= max);
+        match v {
+            // Empty objects have no leaves, keep them as `{}` so the key is not lost
+            Value::Object(map) if self.objects && expand && !map.is_empty() => {
+                for (k, v) in map {
+                    let child = self.child(&path, &k);
+                    self.flatten_into(out, child, v, depth + 1)?;
+                }
+            }
+            Value::Array(mut items) if self.arrays && expand && !items.is_empty() => {
+                let mut rest = Vec::new();
+                if let Some(max) = self.max_array_items.filter(|&max| items.len() > max) {
+                    match self.overflow {
+                        ArrayOverflow::Truncate => items.truncate(max),
+                        ArrayOverflow::Stringify => rest = items.split_off(max),
+                        ArrayOverflow::Error => bail!(
+                            "array \"{}\" has {} items, more than --max-array-items {}",
+                            path,
+                            items.len(),
+                            max
+                        ),
+                    }
+                }
+                for (i, v) in items.into_iter().enumerate() {
+                    let child = self.child(&path, &i.to_string());
+                    self.flatten_into(out, child, v, depth + 1)?;
+                }
+                if !rest.is_empty() {
+                    self.insert(out, self.child(&path, "rest"), Value::Array(rest))?;
+                }
+            }
+            other => self.insert(out, path, other)?,
+        }
+        Ok(())
+    }
+
+    /// Add a leaf, refusing to overwrite another one with the same column name,
+    /// e.g. a literal `"a.b"` key and the flattened `{"a":{"b":...}}`.
+    fn insert(&self, out: &mut Map<String, Value>, path: String, v: Value) -> Result<()> {
+        if out.contains_key(&path) {
+            bail!("column \"{}\" is produced by more than one field after flattening", path);
+        }
+        out.insert(path, v);
+        Ok(())
+    }
+
+    fn child(&self, path: &str, key: &str) -> String {
+        format!("{}{}{}", path, self.separator, key)
+    }
+}
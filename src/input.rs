@@ -0,0 +1,148 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+use anyhow::{bail, Context, Result};
+use tempfile::NamedTempFile;
+
+use crate::compression::{Compression, decode};
+
+enum Source {
+    Stdin,
+    File(String),
+    /// stdin copied into a temporary file so it can be read more than once
+    Spilled(NamedTempFile),
+}
+
+/// Where the JSONL records are read from.
+pub struct Input {
+    source: Source,
+    name: Arc<str>,
+    compression: Compression,
+}
+
+impl Input {
+    /// `-` stands for stdin.
+    pub fn new(path: &str, compression: Compression) -> Self {
+        let (source, name) = match path {
+            "-" => (Source::Stdin, "stdin"),
+            _ => (Source::File(path.to_string()), path),
+        };
+        Self {
+            source,
+            name: name.into(),
+            compression,
+        }
+    }
+
+    /// Name used in messages and in the source file column.
+    pub fn name(&self) -> &Arc<str> {
+        &self.name
+    }
+
+    /// Whether this input is the file at `path`.
+    pub fn is_file(&self, path: &str) -> bool {
+        let Source::File(file) = &self.source else { return false };
+        match (fs::canonicalize(file), fs::canonicalize(path)) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        }
+    }
+
+    /// Make the input readable more than once, as the two-pass header scan needs.
+    pub fn rewindable(self) -> Result<Self> {
+        match self.source {
+            Source::Stdin => {
+                // Still compressed, it is decoded again on every pass
+                let mut spill = NamedTempFile::new().context("Cannot create temporary file to buffer stdin")?;
+                io::copy(&mut io::stdin().lock(), &mut spill).context("Cannot buffer stdin")?;
+                Ok(Self {
+                    source: Source::Spilled(spill),
+                    ..self
+                })
+            }
+            _ => Ok(self),
+        }
+    }
+
+    /// Open the input for reading, decompressing it on the fly.
+    pub fn open(&self) -> Result<Box<dyn BufRead>> {
+        let reader: Box<dyn BufRead> = match &self.source {
+            Source::Stdin => Box::new(io::stdin().lock()),
+            Source::File(path) => {
+                // Stream input to keep memory usage low
+                let infile = File::open(path)
+                    .with_context(|| format!("Cannot open input file: {}", path))?;
+                Box::new(BufReader::new(infile))
+            }
+            Source::Spilled(spill) => Box::new(BufReader::new(spill.reopen()?)),
+        };
+        decode(reader, self.compression)
+    }
+}
+
+/// Location of a record, for messages and the rejects file.
+#[derive(Debug, Clone)]
+pub struct Position {
+    pub file: Arc<str>,
+    /// 1-based line number within the file
+    pub line: usize,
+    /// Byte offset of the start of the line in the (decompressed) file
+    pub offset: u64,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {} of {}", self.line, self.file)
+    }
+}
+
+/// Expand the command-line inputs: glob patterns are matched, directories are listed
+/// (descending into subdirectories with `recursive`), everything else is taken as is.
+pub fn expand_inputs(patterns: &[String], recursive: bool, compression: Compression) -> Result<Vec<Input>> {
+    let mut paths = Vec::new();
+    for pattern in patterns {
+        if pattern != "-" && Path::new(pattern).is_dir() {
+            list_dir(Path::new(pattern), recursive, &mut paths)?;
+        } else if pattern.contains(['*', '?', '[']) {
+            let before = paths.len();
+            for entry in glob::glob(pattern).with_context(|| format!("Invalid glob pattern: {}", pattern))? {
+                let path = entry?;
+                if path.is_dir() {
+                    list_dir(&path, recursive, &mut paths)?;
+                } else {
+                    paths.push(path.to_string_lossy().into_owned());
+                }
+            }
+            if paths.len() == before {
+                bail!("No input files match {}", pattern);
+            }
+        } else {
+            paths.push(pattern.clone());
+        }
+    }
+    Ok(paths.iter().map(|path| Input::new(path, compression)).collect())
+}
+
+/// Files of `dir` in name order, hidden entries skipped.
+fn list_dir(dir: &Path, recursive: bool, paths: &mut Vec<String>) -> Result<()> {
+    let mut entries = fs::read_dir(dir)
+        .with_context(|| format!("Cannot read input directory: {}", dir.display()))?
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        if path.is_dir() {
+            if recursive {
+                list_dir(&path, recursive, paths)?;
+            }
+        } else {
+            paths.push(path.to_string_lossy().into_owned());
+        }
+    }
+    Ok(())
+}
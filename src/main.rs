@@ -1,81 +1,914 @@
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use anyhow::{bail, Context, Result};
-use clap::Parser;
+mod cast;
+mod compression;
+mod datetime;
+mod diagnostic;
+mod dialect;
+mod explode;
+mod flatten;
+mod input;
+mod mapping;
+mod number;
+mod output;
+mod path;
+mod rejects;
+mod render;
+mod root;
+mod schema;
+mod toplevel;
+
+use std::collections::HashSet;
+use std::io::{self, BufRead, Write};
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
 use csv::Writer;
-use serde_json::{Value, from_str};
+use serde_json::{Map, Value, from_str};
+use cast::{Cast, CastFailure, Caster};
+use compression::Compression;
+use datetime::{TimeColumn, TimeConverter};
+use diagnostic::ParseError;
+use dialect::{Dialect, LineEnding, Preset, Quoting, parse_char};
+use explode::{EmptyArray, Exploder};
+use flatten::{ArrayOverflow, Flattener};
+use input::{Input, Position, expand_inputs};
+use mapping::{ColumnMapping, Mapper, read_mapping_file};
+use number::NumberFormat;
+use output::{Output, OutputCompression, is_csv_path};
+use path::JsonPath;
+use rejects::{RejectKind, Rejection, Rejects};
+use render::{BoolFormat, Renderer};
+use root::{EnvelopeField, RootSelector, parse_root};
+use schema::{ColumnOrder, KeyUnion, Profile, ReportFormat};
+use toplevel::{ArrayPolicy, ScalarPolicy, TopLevel};
 
-/// Convert a JSON‑Lines file (one JSON object per line) to a CSV file.
+/// Convert JSON‑Lines files (one JSON object per line) to a CSV file.
 #[derive(Parser, Debug)]
 #[command(author, version, about = "Convert JSONL to CSV", long_about = None)]
+#[command(group = ArgGroup::new("flattening").args(["flatten", "expand_arrays"]).multiple(true))]
+#[command(group = ArgGroup::new("fixed_columns").args(["columns", "columns_file", "map", "map_file"]).multiple(true))]
+#[command(args_conflicts_with_subcommands = true)]
 struct Cli {
-    input: String,
-    output: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Input JSONL files, glob patterns or directories, then the output CSV file when
+    /// `--output` is not given. `-` stands for stdin/stdout; both default to it
+    #[arg(value_name = "PATH")]
+    paths: Vec<String>,
+
+    /// Output CSV file, `-` for stdout; every positional argument is then an input
+    #[arg(short, long, value_name = "PATH")]
+    output: Option<String>,
+
+    /// Descend into subdirectories of input directories
+    #[arg(short, long)]
+    recursive: bool,
+
+    /// Add a column with the input file each row came from
+    #[arg(long, value_name = "NAME", num_args = 0..=1, default_missing_value = "_source_file")]
+    source_file_column: Option<String>,
+
+    /// Skip lines that are not valid JSON objects (or fail to convert) instead of aborting
+    #[arg(long)]
+    skip_invalid: bool,
+
+    /// With `--skip-invalid`, still abort once more than N records have been skipped
+    #[arg(long, value_name = "N", requires = "skip_invalid")]
+    max_errors: Option<u64>,
+
+    /// With `--skip-invalid` or `--on-cast-error reject`, write every skipped line to this
+    /// JSONL file together with its position and the error, so it can be fixed and reprocessed
+    #[arg(long, value_name = "PATH")]
+    rejects: Option<String>,
+
+    /// Compression of the input; detected from its first bytes by default
+    #[arg(long, value_enum, default_value_t = Compression::Auto)]
+    input_compression: Compression,
+
+    /// Compression of the output; picked from its extension (`.gz`, `.zst`) by default
+    #[arg(long, value_enum, default_value_t = OutputCompression::Auto)]
+    output_compression: OutputCompression,
+
+    /// Compression level: 0-9 for gzip (default 6), 1-22 for zstd (default 3)
+    #[arg(long, value_name = "N")]
+    compression_level: Option<u32>,
+
+    /// How the CSV header is discovered
+    #[arg(long, value_enum, default_value_t = HeaderMode::Scan, conflicts_with_all = ["sample", "fixed_columns"])]
+    header_mode: HeaderMode,
+
+    /// Build the header from the first N records, then stream the rest in a single pass
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u64).range(1..),
+        conflicts_with = "fixed_columns"
+    )]
+    sample: Option<u64>,
+
+    /// Write exactly these columns, in this order, instead of discovering them
+    #[arg(long, value_name = "COLS", value_delimiter = ',', conflicts_with = "columns_file")]
+    columns: Option<Vec<String>>,
+
+    /// Like `--columns`, reading one column name per line from a file
+    #[arg(long, value_name = "PATH")]
+    columns_file: Option<String>,
+
+    /// Drop these keys from the discovered columns
+    #[arg(long, value_name = "KEYS", value_delimiter = ',', conflicts_with = "fixed_columns")]
+    exclude: Vec<String>,
+
+    /// Add a column pulled from a path of each record: `NAME=PATH` or `NAME=PATH|DEFAULT`.
+    /// PATH is a JSON Pointer (`/user/id`), a JSONPath (`$.user.id`, `$.items[*].sku`) or a dotted path
+    #[arg(
+        long,
+        value_name = "SPEC",
+        value_parser = ColumnMapping::parse,
+        conflicts_with_all = ["columns", "columns_file", "flattening"]
+    )]
+    map: Vec<ColumnMapping>,
+
+    /// Like `--map`, reading one mapping per line from a file (after the `--map` columns)
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["columns", "columns_file", "flattening"])]
+    map_file: Option<String>,
+
+    /// Delimiter between the values matched by a wildcard path
+    #[arg(long, value_name = "SEP", default_value = "|")]
+    map_join: String,
+
+    /// Expand nested objects into columns named after their path, e.g. `user.address.city`
+    #[arg(long)]
+    flatten: bool,
+
+    /// Expand arrays into one column per item, e.g. `tags.0`, `tags.1`
+    #[arg(long)]
+    expand_arrays: bool,
+
+    /// Only expand the first N items of an array
+    #[arg(long, value_name = "N", requires = "expand_arrays")]
+    max_array_items: Option<usize>,
+
+    /// What to do with the items past `--max-array-items`
+    #[arg(long, value_enum, default_value_t = ArrayOverflow::Truncate, requires = "max_array_items")]
+    array_overflow: ArrayOverflow,
+
+    /// Separator between the path segments of flattened column names
+    #[arg(long, value_name = "SEP", default_value = ".", requires = "flattening")]
+    flatten_separator: String,
+
+    /// Only expand this many levels of nesting, deeper values are written as JSON
+    #[arg(long, value_name = "N", requires = "flattening")]
+    max_depth: Option<usize>,
+
+    /// Write arrays of scalars as text joined with this delimiter, e.g. `a|b|c`
+    #[arg(long, value_name = "SEP")]
+    array_join: Option<String>,
+
+    /// Text written for JSON `null`, e.g. `NULL` or `\N` for database bulk loaders
+    #[arg(long, value_name = "TEXT", default_value = "")]
+    null_token: String,
+
+    /// Text written when a record has no value for a column
+    #[arg(long, value_name = "TEXT", default_value = "")]
+    missing_token: String,
+
+    /// How to write booleans
+    #[arg(long, value_enum, default_value_t = BoolFormat::TrueFalse)]
+    bool_format: BoolFormat,
+
+    /// Round non-integer numbers to N digits after the decimal point
+    #[arg(long, value_name = "N")]
+    decimal_places: Option<usize>,
+
+    /// Write numbers in exponent notation, e.g. `1e20`, as plain digits
+    #[arg(long)]
+    no_scientific: bool,
+
+    /// Write floats with no fractional part, e.g. `1.0`, as integers
+    #[arg(long)]
+    normalize_integers: bool,
+
+    /// Start from a named CSV dialect; the options below override its settings
+    #[arg(long, value_enum)]
+    preset: Option<Preset>,
+
+    /// Field delimiter, `\t` or `tab` for TSV [default: ,]
+    #[arg(long, value_name = "CHAR", value_parser = parse_char)]
+    delimiter: Option<u8>,
+
+    /// Quote character [default: "]
+    #[arg(long, value_name = "CHAR", value_parser = parse_char)]
+    quote: Option<u8>,
+
+    /// Escape quotes inside quoted fields with this character instead of doubling them
+    #[arg(long, value_name = "CHAR", value_parser = parse_char)]
+    escape: Option<u8>,
+
+    /// When fields are quoted [default: necessary]
+    #[arg(long, value_enum)]
+    quote_style: Option<Quoting>,
+
+    /// Record terminator [default: lf]
+    #[arg(long, value_enum)]
+    line_ending: Option<LineEnding>,
+
+    /// Convert the object found at this path of each line instead of the line itself, e.g. `/data` or `_source`
+    #[arg(long, value_name = "PATH", value_parser = parse_root)]
+    root: Option<JsonPath>,
+
+    /// Copy a field from outside the root into every row: `PATH` or `NAME=PATH`, e.g. `meta.ts`
+    #[arg(long, value_name = "SPEC", value_parser = EnvelopeField::parse, requires = "root")]
+    keep_envelope: Vec<EnvelopeField>,
+
+    /// What to do with lines that hold a string, number, boolean or null
+    #[arg(long, value_enum, default_value_t = ScalarPolicy::Reject)]
+    on_scalar: ScalarPolicy,
+
+    /// Column receiving the value of scalar lines with `--on-scalar wrap`
+    #[arg(long, value_name = "NAME", default_value = "value")]
+    value_column: String,
+
+    /// What to do with lines that hold an array
+    #[arg(long, value_enum, default_value_t = ArrayPolicy::Reject)]
+    on_array: ArrayPolicy,
+
+    /// Prefix of the positional columns with `--on-array positional`
+    #[arg(long, value_name = "PREFIX", default_value = "col_")]
+    positional_prefix: String,
+
+    /// Emit one row per element of this array field, repeating the other fields
+    #[arg(long, value_name = "FIELD")]
+    explode: Option<String>,
+
+    /// What to do with records whose exploded array is empty or missing
+    #[arg(long, value_enum, default_value_t = EmptyArray::Keep, requires = "explode")]
+    explode_empty: EmptyArray,
+
+    /// Add a column with the position of each element in the exploded array
+    #[arg(long, value_name = "NAME", requires = "explode")]
+    explode_index: Option<String>,
+
+    /// Reformat a timestamp column: `COLUMN` or `COLUMN:KIND`, KIND being auto (default),
+    /// iso8601, rfc2822, epoch-s, epoch-ms, epoch-us or epoch-ns
+    #[arg(long, value_name = "SPEC", value_parser = TimeColumn::parse)]
+    time: Vec<TimeColumn>,
+
+    /// strftime format of reformatted timestamps
+    #[arg(long, value_name = "FORMAT", default_value = "%Y-%m-%dT%H:%M:%S%.f%:z", requires = "time")]
+    time_format: String,
+
+    /// Timezone of reformatted timestamps, e.g. `UTC` or `Europe/Berlin`
+    #[arg(long, value_name = "TZ", default_value = "UTC", requires = "time")]
+    timezone: String,
+
+    /// Convert a column to a type: `COLUMN:TYPE`, TYPE being int, float, decimal, bool,
+    /// string, date or json
+    #[arg(long, value_name = "SPEC", value_parser = Cast::parse)]
+    cast: Vec<Cast>,
+
+    /// What to do with values that cannot be cast
+    #[arg(long, value_enum, default_value_t = CastFailure::Error, requires = "cast")]
+    on_cast_error: CastFailure,
+
+    /// What to do with keys that are not part of the header (first/sample modes)
+    #[arg(long, value_enum, default_value_t = NewKeyPolicy::Warn)]
+    on_new_key: NewKeyPolicy,
+
+    /// Name of the column that collects unknown keys with `--on-new-key extra-column`
+    #[arg(long, value_name = "NAME", default_value = "_extra")]
+    extra_column: String,
+
+    /// Order of the columns in the CSV header
+    #[arg(long, value_enum, default_value_t = ColumnOrder::FirstSeen)]
+    column_order: ColumnOrder,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Report the JSON types, nullability and example values of every key instead of converting
+    #[command(visible_alias = "infer")]
+    Schema(SchemaArgs),
 }
 
-fn json_to_string(v: &Value) -> String {
-    match v {
-        Value::Null => String::new(),
-        Value::Bool(b) => b.to_string(),
-        Value::Number(n) => n.to_string(),
-        Value::String(s) => s.clone(),
-        // For arrays/objects, fall back to compact JSON representation
-        other => other.to_string(),
+#[derive(Args, Debug)]
+struct SchemaArgs {
+    /// Input JSONL files, glob patterns or directories, `-` for stdin (the default)
+    #[arg(value_name = "PATH")]
+    paths: Vec<String>,
+
+    /// Descend into subdirectories of input directories
+    #[arg(short, long)]
+    recursive: bool,
+
+    /// Compression of the inputs; detected from their first bytes by default
+    #[arg(long, value_enum, default_value_t = Compression::Auto)]
+    input_compression: Compression,
+
+    /// Skip lines that are not valid JSON objects instead of aborting
+    #[arg(long)]
+    skip_invalid: bool,
+
+    /// Profile the object found at this path of each line instead of the line itself
+    #[arg(long, value_name = "PATH", value_parser = parse_root)]
+    root: Option<JsonPath>,
+
+    /// Report nested objects as dotted columns, as `--flatten` writes them
+    #[arg(long)]
+    flatten: bool,
+
+    /// Separator between the path segments of flattened column names
+    #[arg(long, value_name = "SEP", default_value = ".", requires = "flatten")]
+    flatten_separator: String,
+
+    /// Number of distinct example values shown per column
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    examples: usize,
+
+    /// Print the report as a table or as JSON
+    #[arg(long, value_enum, default_value_t = ReportFormat::Table)]
+    format: ReportFormat,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum HeaderMode {
+    /// Read the whole input once to collect the union of all keys (two passes)
+    Scan,
+    /// Take the keys of the first record only (single pass, later keys are dropped)
+    First,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum NewKeyPolicy {
+    /// Drop the value silently
+    Ignore,
+    /// Drop the value and report the line number and key on stderr (once per key)
+    Warn,
+    /// Abort the conversion
+    Error,
+    /// Collect unknown keys into one JSON-encoded column appended to the header
+    ExtraColumn,
+}
+
+/// Reshapes each parsed record into the row that is written to the CSV.
+struct Transform {
+    source_column: Option<String>,
+    root: Option<RootSelector>,
+    top_level: TopLevel,
+    exploder: Option<Exploder>,
+    mapper: Option<Mapper>,
+    flattener: Option<Flattener>,
+    times: Option<TimeConverter>,
+    caster: Option<Caster>,
+}
+
+impl Transform {
+    /// Descend into the configured root of a parsed line.
+    fn select_root(&self, value: Value) -> Result<Value> {
+        match &self.root {
+            Some(root) => root.select(value),
+            None => Ok(value),
+        }
+    }
+
+    /// Turn one record into the rows it produces; explode runs first so array elements get flattened too,
+    /// timestamps are reformatted last so `--time` refers to output column names.
+    fn apply(&self, obj: Map<String, Value>) -> Result<Vec<Map<String, Value>>> {
+        let rows = match &self.exploder {
+            Some(exploder) => exploder.explode(obj),
+            None => vec![obj],
+        };
+        let mut rows = if let Some(mapper) = &self.mapper {
+            rows.into_iter().map(|row| mapper.map(row)).collect()
+        } else if let Some(flattener) = &self.flattener {
+            rows.into_iter().map(|row| flattener.flatten(row)).collect::<Result<_>>()?
+        } else {
+            rows
+        };
+        if let Some(times) = &self.times {
+            for row in &mut rows {
+                times.convert(row)?;
+            }
+        }
+        Ok(rows)
     }
 }
 
-fn main() -> Result<()> {
-    // Parse CLI flags
-    let cli = Cli::parse();
+/// Parse one line and reshape it into the rows it produces.
+fn parse_record(line: &str, pos: &Position, transform: &Transform) -> Result<Vec<Map<String, Value>>, Rejection> {
+    // Parse the JSON object in this line
+    let value: Value = from_str(line).map_err(|e| Rejection::parse(pos, line.as_bytes(), ParseError::json(&e, line)))?;
 
-    // Stream input to keep memory usage low
-    let infile = File::open(&cli.input)
-        .with_context(|| format!("Cannot open input file: {}", &cli.input))?;
-    let reader = BufReader::new(infile);
+    let value = transform
+        .select_root(value)
+        .map_err(|e| Rejection::new(RejectKind::Convert, pos, line.as_bytes(), e))?;
 
-    let mut wtr = Writer::from_path(&cli.output)
-        .with_context(|| format!("Cannot create output file: {}", &cli.output))?;
+    let records = transform
+        .top_level
+        .records(value)
+        .map_err(|e| Rejection::new(RejectKind::NotObject, pos, line.as_bytes(), e))?;
 
-    let mut headers: Vec<String> = Vec::new();
-    let mut header_written = false;
+    let mut rows = Vec::new();
+    for obj in records {
+        for mut row in transform
+            .apply(obj)
+            .map_err(|e| Rejection::new(RejectKind::Convert, pos, line.as_bytes(), e))?
+        {
+            if let Some(caster) = &transform.caster {
+                caster.cast(&mut row).map_err(|e| Rejection::new(RejectKind::Cast, pos, line.as_bytes(), e))?;
+            }
+            if let Some(col) = &transform.source_column {
+                if row.contains_key(col) {
+                    let cause = anyhow!("record has a field \"{}\", the name of the source file column", col);
+                    return Err(Rejection::new(RejectKind::Convert, pos, line.as_bytes(), cause));
+                }
+                row.insert(col.clone(), Value::String(pos.file.to_string()));
+            }
+            rows.push(row);
+        }
+    }
+    Ok(rows)
+}
+
+/// Stream the rows produced by every JSON object of the inputs to `f`,
+/// together with the position and raw bytes of the line. Records that fail to parse
+/// or convert are handed to `rejects`.
+fn for_each_record<F>(inputs: &[Input], transform: &Transform, rejects: &mut Rejects, mut f: F) -> Result<()>
+where
+    F: FnMut(&mut Rejects, &Position, &[u8], Map<String, Value>) -> Result<()>,
+{
+    for input in inputs {
+        let mut reader = input.open()?;
+        let mut buf = Vec::new();
+        let mut offset = 0;
+        let mut line_no = 0;
+
+        // Read each line from the input file, keeping track of where it starts
+        loop {
+            buf.clear();
+            let n = reader
+                .read_until(b'\n', &mut buf)
+                .with_context(|| format!("Cannot read {}", input.name()))?;
+            if n == 0 {
+                break;
+            }
+            line_no += 1;
+            let start = offset;
+            offset += n as u64;
 
-    // Read each line from the input file
-    for (idx, line) in reader.lines().enumerate() {
-        let line = line?;
-        // Skip blank lines
-        if line.trim().is_empty() {
-            continue;
+            let bytes = buf.strip_suffix(b"\n").unwrap_or(&buf);
+            let bytes = bytes.strip_suffix(b"\r").unwrap_or(bytes);
+            let text = std::str::from_utf8(bytes);
+            let pos = Position {
+                file: input.name().clone(),
+                line: line_no,
+                offset: start,
+            };
+            let line = match text {
+                Ok(line) => line,
+                Err(e) => {
+                    let err = ParseError::utf8(&e, &String::from_utf8_lossy(bytes));
+                    rejects.handle(Rejection::parse(&pos, bytes, err).into())?;
+                    continue;
+                }
+            };
+            // Skip blank lines
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let rows = match parse_record(line, &pos, transform) {
+                Ok(rows) => rows,
+                Err(rejection) => {
+                    rejects.handle(rejection.into())?;
+                    continue;
+                }
+            };
+            for row in rows {
+                f(rejects, &pos, bytes, row)?;
+            }
         }
+    }
 
-        // Parse the JSON object in this line
-        let value: Value = from_str(&line)
-            .with_context(|| format!("JSON parse error on line {}", idx + 1))?;
+    Ok(())
+}
 
-        let obj = match value {
-            Value::Object(map) => map,
-            _ => bail!("Line {} is not a JSON object", idx + 1),
-        };
+/// Read column names from a file, one per line. Blank lines and `#` comments are skipped.
+fn read_columns_file(path: &str) -> Result<Vec<String>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Cannot read columns file: {}", path))?;
+    let columns: Vec<String> = content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(String::from)
+        .collect();
+    if columns.is_empty() {
+        bail!("Columns file {} lists no columns", path);
+    }
+    Ok(columns)
+}
 
-        // Capture header from the first record
-        if !header_written {
-            headers = obj.keys().cloned().collect();
-            wtr.write_record(&headers)?;
-            header_written = true;
+/// First pass: collect the union of keys over every record of the inputs.
+fn scan_headers(
+    inputs: &[Input],
+    transform: &Transform,
+    rejects: &mut Rejects,
+    order: ColumnOrder,
+) -> Result<Vec<String>> {
+    let mut keys = KeyUnion::default();
+    for_each_record(inputs, transform, rejects, |_, _, _, obj| {
+        keys.add(&obj);
+        Ok(())
+    })?;
+    Ok(keys.into_headers(order))
+}
+
+/// Writes records in header order once the header is known.
+struct RowWriter {
+    wtr: Writer<Output>,
+    headers: Vec<String>,
+    known: HashSet<String>,
+    excluded: HashSet<String>,
+    on_new_key: NewKeyPolicy,
+    extra_column: String,
+    renderer: Renderer,
+    warned: HashSet<String>,
+}
+
+impl RowWriter {
+    fn new(
+        wtr: Writer<Output>,
+        excluded: HashSet<String>,
+        on_new_key: NewKeyPolicy,
+        extra_column: String,
+        renderer: Renderer,
+    ) -> Self {
+        Self {
+            wtr,
+            headers: Vec::new(),
+            known: HashSet::new(),
+            excluded,
+            on_new_key,
+            extra_column,
+            renderer,
+            warned: HashSet::new(),
         }
+    }
 
-        // For each following record, output fields in header order.
+    fn write_header(&mut self, mut headers: Vec<String>) -> Result<()> {
+        headers.retain(|k| !self.excluded.contains(k));
+        // Empty input: leave the output empty, unless the extra column still needs its header
+        if !headers.is_empty() || self.on_new_key == NewKeyPolicy::ExtraColumn {
+            if self.on_new_key == NewKeyPolicy::ExtraColumn {
+                self.wtr.write_record(headers.iter().chain([&self.extra_column]))?;
+            } else {
+                self.wtr.write_record(&headers)?;
+            }
+        }
+        // Excluded keys are dropped on purpose, they do not count as new keys
+        self.known = headers.iter().chain(&self.excluded).cloned().collect();
+        self.headers = headers;
+        Ok(())
+    }
+
+    fn write_row(&mut self, pos: &Position, raw: &[u8], obj: &Map<String, Value>) -> Result<()> {
+        // Keys outside the header would be lost, apply the configured policy
+        let mut extra = Map::new();
+        for (k, v) in obj {
+            if self.known.contains(k) {
+                continue;
+            }
+            match self.on_new_key {
+                NewKeyPolicy::Ignore => {}
+                NewKeyPolicy::ExtraColumn => {
+                    extra.insert(k.clone(), v.clone());
+                }
+                NewKeyPolicy::Warn => {
+                    if self.warned.insert(k.clone()) {
+                        eprintln!("warning: {}: key \"{}\" is not in the CSV header, value dropped", pos, k);
+                    }
+                }
+                NewKeyPolicy::Error => {
+                    let cause = anyhow!(
+                        "key \"{}\" is not in the CSV header ({} columns built from the leading records); \
+                         use --sample with a larger window or --on-new-key extra-column",
+                        k,
+                        self.headers.len()
+                    );
+                    return Err(Rejection::new(RejectKind::Schema, pos, raw, cause).into());
+                }
+            }
+        }
+
+        // Output fields in header order.
         // If a field is missing, write an empty string.
-        let record: Vec<String> = headers
+        let mut record: Vec<String> = self
+            .headers
             .iter()
-            .map(|k| obj.get(k).map(json_to_string).unwrap_or_default())
+            .map(|k| self.renderer.render_field(obj.get(k)))
             .collect();
 
-        wtr.write_record(&record)?;
+        if self.on_new_key == NewKeyPolicy::ExtraColumn {
+            record.push(if extra.is_empty() { String::new() } else { Value::Object(extra).to_string() });
+        }
+
+        // Without any column there is no header either, a row would only be a stray `""`
+        if record.is_empty() {
+            return Ok(());
+        }
+        self.wtr.write_record(&record)?;
+        Ok(())
+    }
+
+    /// Flush the CSV writer and finish the output, including any compression trailer.
+    fn finish(self) -> Result<()> {
+        let output = self.wtr.into_inner().map_err(|e| e.into_error())?;
+        output.finish()?;
+        Ok(())
+    }
+}
+
+/// Write the header built from the buffered sample records, then the records themselves.
+fn flush_sample(
+    out: &mut RowWriter,
+    pending: &mut Vec<(Position, Vec<u8>, Map<String, Value>)>,
+    order: ColumnOrder,
+    rejects: &mut Rejects,
+) -> Result<()> {
+    let mut keys = KeyUnion::default();
+    for (_, _, obj) in pending.iter() {
+        keys.add(obj);
+    }
+    out.write_header(keys.into_headers(order))?;
+    for (pos, raw, obj) in pending.drain(..) {
+        out.write_row(&pos, &raw, &obj).or_else(|err| rejects.handle(err))?;
+    }
+    Ok(())
+}
+
+/// Scan the inputs and print the types observed for every key.
+fn infer_schema(args: SchemaArgs) -> Result<()> {
+    let paths = if args.paths.is_empty() { vec!["-".to_string()] } else { args.paths };
+    let inputs = expand_inputs(&paths, args.recursive, args.input_compression)?;
+    let transform = Transform {
+        source_column: None,
+        root: args.root.map(|root| RootSelector { root, envelope: Vec::new() }),
+        top_level: TopLevel {
+            scalars: ScalarPolicy::Reject,
+            arrays: ArrayPolicy::Reject,
+            value_column: "value".to_string(),
+            positional_prefix: "col_".to_string(),
+        },
+        exploder: None,
+        mapper: None,
+        flattener: args.flatten.then_some(Flattener {
+            separator: args.flatten_separator,
+            objects: true,
+            arrays: false,
+            max_depth: None,
+            max_array_items: None,
+            overflow: ArrayOverflow::Truncate,
+        }),
+        times: None,
+        caster: None,
+    };
+
+    let mut rejects = Rejects::new(args.skip_invalid, None, true);
+    let mut profile = Profile::new(args.examples);
+    for_each_record(&inputs, &transform, &mut rejects, |_, _, _, obj| {
+        profile.add(&obj);
+        Ok(())
+    })?;
+
+    let mut stdout = io::stdout().lock();
+    match args.format {
+        ReportFormat::Table => write!(stdout, "{}", profile.table())?,
+        ReportFormat::Json => writeln!(stdout, "{}", serde_json::to_string_pretty(&profile.to_json())?)?,
+    }
+    stdout.flush()?;
+    if let Some(summary) = rejects.summary() {
+        eprintln!("{}", summary);
+    }
+    Ok(())
+}
+
+/// Whether a positional path can be taken as the output: it has a (possibly compressed) CSV
+/// or TSV extension, or does not exist yet.
+fn looks_like_output(path: &str) -> bool {
+    is_csv_path(path) || (!path.contains(['*', '?', '[']) && !std::path::Path::new(path).exists())
+}
+
+fn main() -> Result<()> {
+    match run() {
+        // The reader went away, there is nobody left to report to
+        Err(err) if is_broken_pipe(&err) => Ok(()),
+        result => result,
     }
+}
 
-    println!("Conversion from {} to {} successfully completed.", cli.input, cli.output);
+fn run() -> Result<()> {
+    // Parse CLI flags
+    let cli = Cli::parse();
+    if let Some(Command::Schema(args)) = cli.command {
+        return infer_schema(args);
+    }
+    if cli.rejects.is_some() && !cli.skip_invalid && cli.on_cast_error != CastFailure::Reject {
+        bail!("--rejects requires --skip-invalid or --on-cast-error reject");
+    }
 
-    wtr.flush()?;
+    // Without --output a second positional path is the output, as in `jsonl2csv in.jsonl out.csv`,
+    // but only when it cannot be an input matched by a shell glob
+    let mut paths = cli.paths;
+    let output_path = match cli.output {
+        Some(path) => path,
+        None if paths.len() == 2 && looks_like_output(&paths[1]) => paths.pop().unwrap_or_default(),
+        None if paths.len() == 2 => bail!(
+            "{} already exists and is not a .csv or .tsv file; name the output with --output to write to it",
+            paths[1]
+        ),
+        None if paths.len() > 2 => bail!("Several inputs given, name the output file with --output"),
+        None => "-".to_string(),
+    };
+    if paths.is_empty() {
+        paths.push("-".to_string());
+    }
+
+    let mut dialect = cli.preset.map(Dialect::preset).unwrap_or_default();
+    dialect.delimiter = cli.delimiter.unwrap_or(dialect.delimiter);
+    dialect.quote = cli.quote.unwrap_or(dialect.quote);
+    dialect.escape = cli.escape.or(dialect.escape);
+    dialect.quoting = cli.quote_style.unwrap_or(dialect.quoting);
+    dialect.line_ending = cli.line_ending.unwrap_or(dialect.line_ending);
+
+    let mut inputs = expand_inputs(&paths, cli.recursive, cli.input_compression)?;
+    if let Some(input) = inputs.iter().find(|input| input.is_file(&output_path)) {
+        bail!("The output file {} is also an input", input.name());
+    }
+    let output = Output::create(&output_path, cli.output_compression, cli.compression_level)?;
+    let wtr = dialect.builder().from_writer(output);
+
+    // Number of leading records the header is built from, `None` when the whole input is scanned
+    let sample_size = match (cli.sample, cli.header_mode) {
+        (Some(n), _) => Some(n as usize),
+        (None, HeaderMode::First) => Some(1),
+        (None, HeaderMode::Scan) => None,
+    };
+
+    let renderer = Renderer {
+        array_join: cli.array_join,
+        null_token: cli.null_token,
+        missing_token: cli.missing_token,
+        bools: cli.bool_format,
+        numbers: NumberFormat {
+            decimal_places: cli.decimal_places,
+            plain: cli.no_scientific,
+            normalize_integers: cli.normalize_integers,
+        },
+    };
+
+    let mut mappings = cli.map;
+    if let Some(path) = &cli.map_file {
+        mappings.extend(read_mapping_file(path)?);
+    }
+    let mapper = (!mappings.is_empty()).then(|| Mapper {
+        columns: mappings,
+        join: cli.map_join,
+        renderer: renderer.clone(),
+    });
+
+    // Explicit columns bypass key discovery altogether
+    let mut explicit = match (cli.columns, &cli.columns_file, &mapper) {
+        (Some(cols), _, _) => Some(cols),
+        (None, Some(path), _) => Some(read_columns_file(path)?),
+        (None, None, Some(mapper)) => Some(mapper.headers()),
+        (None, None, None) => None,
+    };
+    if let (Some(cols), Some(col)) = (&mut explicit, &cli.source_file_column)
+        && !cols.contains(col)
+    {
+        cols.push(col.clone());
+    }
+
+    let transform = Transform {
+        source_column: cli.source_file_column,
+        root: cli.root.map(|root| RootSelector {
+            root,
+            envelope: cli.keep_envelope,
+        }),
+        top_level: TopLevel {
+            scalars: cli.on_scalar,
+            arrays: cli.on_array,
+            value_column: cli.value_column,
+            positional_prefix: cli.positional_prefix,
+        },
+        exploder: cli.explode.map(|field| Exploder {
+            field,
+            index_column: cli.explode_index,
+            empty: cli.explode_empty,
+        }),
+        mapper,
+        flattener: (cli.flatten || cli.expand_arrays).then(|| Flattener {
+            separator: cli.flatten_separator.clone(),
+            objects: cli.flatten,
+            arrays: cli.expand_arrays,
+            max_depth: cli.max_depth,
+            max_array_items: cli.max_array_items,
+            overflow: cli.array_overflow,
+        }),
+        times: if cli.time.is_empty() {
+            None
+        } else {
+            Some(TimeConverter::new(cli.time, cli.time_format, &cli.timezone)?)
+        },
+        caster: (!cli.cast.is_empty()).then_some(Caster {
+            casts: cli.cast,
+            on_error: cli.on_cast_error,
+        }),
+    };
+
+    // In scan mode the header holds every key, so no record can bring a new one.
+    // With explicit columns every other key is left out on purpose.
+    let on_new_key = if sample_size.is_some() && explicit.is_none() {
+        cli.on_new_key
+    } else {
+        NewKeyPolicy::Ignore
+    };
+    let excluded = cli.exclude.into_iter().collect();
+    let mut out = RowWriter::new(wtr, excluded, on_new_key, cli.extra_column, renderer);
+
+    let mut header_written = false;
+    let mut pending: Vec<(Position, Vec<u8>, Map<String, Value>)> = Vec::new();
+
+    // With explicit columns or in scan mode the header is known before any row is written
+    if let Some(cols) = explicit {
+        out.write_header(cols)?;
+        header_written = true;
+    } else if sample_size.is_none() {
+        // The scan reads the inputs twice, so stdin is buffered in a temporary file first
+        inputs = inputs.into_iter().map(Input::rewindable).collect::<Result<_>>()?;
+        // Skipped records are reported, written to the rejects file and counted
+        // against --max-errors by the second pass only
+        let mut scan_rejects = Rejects::new(cli.skip_invalid, None, false);
+        if cli.on_cast_error == CastFailure::Reject {
+            scan_rejects.always_skip(RejectKind::Cast);
+        }
+        out.write_header(scan_headers(&inputs, &transform, &mut scan_rejects, cli.column_order)?)?;
+        header_written = true;
+    }
+
+    let mut rejects = Rejects::new(cli.skip_invalid, cli.max_errors, true);
+    if cli.on_cast_error == CastFailure::Reject {
+        rejects.always_skip(RejectKind::Cast);
+    }
+    if let Some(path) = &cli.rejects {
+        rejects.write_to(path)?;
+    }
+    for_each_record(&inputs, &transform, &mut rejects, |rejects, pos, raw, obj| {
+        if header_written {
+            return out.write_row(pos, raw, &obj).or_else(|err| rejects.handle(err));
+        }
+        // The line is only kept while the sample is buffered
+        pending.push((pos.clone(), raw.to_vec(), obj));
+        if sample_size.is_some_and(|n| pending.len() >= n) {
+            flush_sample(&mut out, &mut pending, cli.column_order, rejects)?;
+            header_written = true;
+        }
+        Ok(())
+    })?;
+
+    // Input shorter than the sample window
+    if !header_written {
+        flush_sample(&mut out, &mut pending, cli.column_order, &mut rejects)?;
+    }
+
+    out.finish()?;
+    rejects.finish()?;
+
+    // Report on stderr so the message never ends up in CSV piped to stdout
+    let from = match inputs.as_slice() {
+        [input] => input.name().to_string(),
+        _ => format!("{} files", inputs.len()),
+    };
+    eprintln!(
+        "Conversion from {} to {} successfully completed.",
+        from,
+        if output_path == "-" { "stdout" } else { &output_path },
+    );
+    if let Some(summary) = rejects.summary() {
+        eprintln!("{}", summary);
+    }
     Ok(())
 }
 
+/// Whether the error comes from writing to a closed pipe, e.g. `jsonl2csv in.jsonl | head`.
+fn is_broken_pipe(err: &anyhow::Error) -> bool {
+    err.chain().any(|cause| {
+        let io_err = match cause.downcast_ref::<csv::Error>() {
+            Some(csv_err) => match csv_err.kind() {
+                csv::ErrorKind::Io(e) => Some(e),
+                _ => None,
+            },
+            None => cause.downcast_ref::<io::Error>(),
+        };
+        io_err.is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+    })
+}
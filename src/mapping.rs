@@ -0,0 +1,79 @@
+use anyhow::{Context, Result};
+use serde_json::{Map, Value};
+
+use crate::path::JsonPath;
+use crate::render::Renderer;
+
+/// One output column pulled from a path of the record: `NAME=PATH` or `NAME=PATH|DEFAULT`.
+#[derive(Debug, Clone)]
+pub struct ColumnMapping {
+    pub name: String,
+    path: JsonPath,
+    /// Written when the path matches nothing
+    default: Option<String>,
+}
+
+impl ColumnMapping {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (name, rest) = spec
+            .split_once('=')
+            .with_context(|| format!("Invalid column mapping \"{}\", expected NAME=PATH", spec))?;
+        let (path, default) = match rest.split_once('|') {
+            Some((path, default)) => (path, Some(default.to_string())),
+            None => (rest, None),
+        };
+        Ok(Self {
+            name: name.trim().to_string(),
+            path: JsonPath::parse(path.trim())?,
+            default,
+        })
+    }
+}
+
+/// Read column mappings from a file, one `NAME=PATH[|DEFAULT]` per line.
+/// Blank lines and `#` comments are skipped.
+pub fn read_mapping_file(path: &str) -> Result<Vec<ColumnMapping>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Cannot read mapping file: {}", path))?;
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(ColumnMapping::parse)
+        .collect()
+}
+
+/// Builds rows from column mappings instead of the record's own keys.
+pub struct Mapper {
+    pub columns: Vec<ColumnMapping>,
+    /// Delimiter between the values matched by a wildcard path
+    pub join: String,
+    pub renderer: Renderer,
+}
+
+impl Mapper {
+    pub fn headers(&self) -> Vec<String> {
+        self.columns.iter().map(|c| c.name.clone()).collect()
+    }
+
+    pub fn map(&self, obj: Map<String, Value>) -> Map<String, Value> {
+        let root = Value::Object(obj);
+        let mut row = Map::new();
+        for col in &self.columns {
+            let matches = col.path.select(&root);
+            let value = if matches.is_empty() {
+                // Leave the column missing when there is no default
+                col.default.clone().map(Value::String)
+            } else if col.path.has_wildcard() {
+                let joined = matches.iter().map(|v| self.renderer.render(v)).collect::<Vec<_>>();
+                Some(Value::String(joined.join(&self.join)))
+            } else {
+                Some(matches[0].clone())
+            };
+            if let Some(value) = value {
+                row.insert(col.name.clone(), value);
+            }
+        }
+        row
+    }
+}
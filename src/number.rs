@@ -0,0 +1,219 @@
+/// How numbers are written. Numbers keep the exact text they had in the source
+/// unless one of these options asks for a rewrite. Numbers whose exponent is
+/// beyond ±4096 are always left as written.
+#[derive(Clone, Debug, Default)]
+pub struct NumberFormat {
+    /// Round non-integer numbers to this many digits after the decimal point
+    pub decimal_places: Option<usize>,
+    /// Expand exponents, e.g. `1e20` becomes `100000000000000000000`
+    pub plain: bool,
+    /// Write floats with no fractional part as integers, e.g. `1.0` becomes `1`
+    pub normalize_integers: bool,
+}
+
+impl NumberFormat {
+    pub fn format(&self, text: &str) -> String {
+        let is_float = text.contains(['.', 'e', 'E']);
+        if !is_float {
+            return text.to_string();
+        }
+        let Some(mut d) = Decimal::parse(text) else {
+            return text.to_string();
+        };
+        let mut rewrite = self.plain && text.contains(['e', 'E']);
+        if let Some(places) = self.decimal_places {
+            d.round(places);
+            rewrite = true;
+        }
+        if self.normalize_integers && d.frac.bytes().all(|b| b == b'0') {
+            d.frac.clear();
+            rewrite = true;
+        }
+        if rewrite {
+            return d.to_string();
+        }
+        text.to_string()
+    }
+}
+
+/// A JSON number written without an exponent, keeping every digit.
+/// Numbers with an exponent beyond `MAX_EXPONENT` are returned as written.
+pub fn plain(text: &str) -> String {
+    match Decimal::parse(text) {
+        Some(d) => d.to_string(),
+        None => text.to_string(),
+    }
+}
+
+/// Largest exponent that is expanded into digits; bigger ones are left as written
+/// rather than producing, or allocating, an absurdly long number.
+pub const MAX_EXPONENT: i64 = 4096;
+
+/// A number written out in plain decimal notation, digit by digit.
+struct Decimal {
+    negative: bool,
+    int: String,
+    frac: String,
+}
+
+impl Decimal {
+    /// Parse a JSON number token, moving the decimal point by its exponent.
+    fn parse(text: &str) -> Option<Self> {
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text),
+        };
+        let (mantissa, exp) = match text.find(['e', 'E']) {
+            Some(i) => (&text[..i], text[i + 1..].parse::<i64>().ok()?),
+            None => (text, 0),
+        };
+        if exp.abs() > MAX_EXPONENT {
+            return None;
+        }
+        let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+        let digits = format!("{}{}", int, frac);
+        // Position of the decimal point within `digits`
+        let point = (int.len() as i64).checked_add(exp)?;
+        let (int, frac) = if point <= 0 {
+            (String::new(), "0".repeat(point.unsigned_abs() as usize) + &digits)
+        } else if point as usize >= digits.len() {
+            (digits.clone() + &"0".repeat(point as usize - digits.len()), String::new())
+        } else {
+            let (i, f) = digits.split_at(point as usize);
+            (i.to_string(), f.to_string())
+        };
+        let int = int.trim_start_matches('0').to_string();
+        Some(Self { negative, int, frac })
+    }
+
+    /// Round half away from zero to `places` fractional digits, padding with zeros.
+    fn round(&mut self, places: usize) {
+        if self.frac.len() <= places {
+            self.frac.push_str(&"0".repeat(places - self.frac.len()));
+            return;
+        }
+        let round_up = self.frac.as_bytes()[places] >= b'5';
+        self.frac.truncate(places);
+        if round_up {
+            let mut digits = format!("{}{}", self.int, self.frac).into_bytes();
+            let mut i = digits.len();
+            loop {
+                if i == 0 {
+                    digits.insert(0, b'1');
+                    break;
+                }
+                i -= 1;
+                if digits[i] == b'9' {
+                    digits[i] = b'0';
+                } else {
+                    digits[i] += 1;
+                    break;
+                }
+            }
+            let digits = String::from_utf8(digits).expect("digits are ASCII");
+            let (int, frac) = digits.split_at(digits.len() - places);
+            self.int = int.to_string();
+            self.frac = frac.to_string();
+        }
+    }
+}
+
+impl std::fmt::Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let zero = self.int.is_empty() && self.frac.bytes().all(|b| b == b'0');
+        if self.negative && !zero {
+            f.write_str("-")?;
+        }
+        f.write_str(if self.int.is_empty() { "0" } else { &self.int })?;
+        if !self.frac.is_empty() {
+            write!(f, ".{}", self.frac)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(text: &str, decimal_places: Option<usize>, plain: bool, normalize_integers: bool) -> String {
+        NumberFormat { decimal_places, plain, normalize_integers }.format(text)
+    }
+
+    fn round(text: &str, places: usize) -> String {
+        let mut d = Decimal::parse(text).unwrap();
+        d.round(places);
+        d.to_string()
+    }
+
+    #[test]
+    fn keeps_source_text_by_default() {
+        for text in ["12345678901234567890123", "0.1000000000000000055511151231257827", "1e20", "1.0", "-0.0", "2.5E-3"] {
+            assert_eq!(format(text, None, false, false), text);
+        }
+    }
+
+    #[test]
+    fn expands_exponents() {
+        assert_eq!(format("1e20", None, true, false), "100000000000000000000");
+        assert_eq!(format("1.5E+3", None, true, false), "1500");
+        assert_eq!(format("2.5e-3", None, true, false), "0.0025");
+        assert_eq!(format("-1.25e1", None, true, false), "-12.5");
+        assert_eq!(format("123e-5", None, true, false), "0.00123");
+        // Numbers without an exponent are left alone
+        assert_eq!(format("1.50", None, true, false), "1.50");
+    }
+
+    #[test]
+    fn leaves_huge_exponents_as_written() {
+        assert_eq!(format("1e99999999999999", None, true, false), "1e99999999999999");
+        assert_eq!(format("1e-99999999999999", Some(2), false, false), "1e-99999999999999");
+        assert_eq!(format("1e99999999999999999999", None, true, true), "1e99999999999999999999");
+        assert_eq!(format("1e4096", None, true, false).len(), 4097);
+        assert_eq!(plain("1e4097"), "1e4097");
+    }
+
+    #[test]
+    fn normalizes_integer_valued_floats() {
+        assert_eq!(format("1.0", None, false, true), "1");
+        assert_eq!(format("-3.000", None, false, true), "-3");
+        assert_eq!(format("2.5e3", None, false, true), "2500");
+        assert_eq!(format("-0.0", None, false, true), "0");
+        assert_eq!(format("1.5", None, false, true), "1.5");
+        assert_eq!(format("7", None, false, true), "7");
+    }
+
+    #[test]
+    fn rounds_to_decimal_places() {
+        assert_eq!(format("0.125", Some(2), false, false), "0.13");
+        assert_eq!(format("0.124", Some(2), false, false), "0.12");
+        assert_eq!(format("1.5", Some(3), false, false), "1.500");
+        assert_eq!(format("1e20", Some(1), false, false), "100000000000000000000.0");
+        assert_eq!(format("0.999", Some(2), false, true), "1");
+        // Integers are not given decimals
+        assert_eq!(format("5", Some(2), false, false), "5");
+    }
+
+    #[test]
+    fn round_carries_into_new_digits() {
+        assert_eq!(round("9.995", 2), "10.00");
+        assert_eq!(round("99.96", 1), "100.0");
+        assert_eq!(round("0.95", 1), "1.0");
+        assert_eq!(round("-9.5", 0), "-10");
+        assert_eq!(round("0.05", 1), "0.1");
+    }
+
+    #[test]
+    fn round_drops_sign_of_zero() {
+        assert_eq!(round("-0.004", 2), "0.00");
+        assert_eq!(round("-0.0", 1), "0.0");
+        assert_eq!(round("-0.005", 2), "-0.01");
+    }
+
+    #[test]
+    fn round_handles_small_exponents() {
+        assert_eq!(round("1e-10", 3), "0.000");
+        assert_eq!(round("5e-4", 3), "0.001");
+        assert_eq!(round("1.23456e2", 2), "123.46");
+    }
+}
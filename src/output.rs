@@ -0,0 +1,106 @@
+use std::fs::File;
+use std::io::{self, Write};
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use flate2::write::GzEncoder;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputCompression {
+    /// Pick from the output extension: `.gz` or `.zst`
+    Auto,
+    None,
+    Gzip,
+    Zstd,
+}
+
+/// Extensions that pick the output compression with `OutputCompression::Auto`.
+const COMPRESSED_EXTENSIONS: [(&str, OutputCompression); 3] = [
+    (".gz", OutputCompression::Gzip),
+    (".zst", OutputCompression::Zstd),
+    (".zstd", OutputCompression::Zstd),
+];
+
+/// Whether `path` names a CSV or TSV file, possibly with a compression extension.
+pub fn is_csv_path(path: &str) -> bool {
+    let lower = path.to_ascii_lowercase();
+    let base = COMPRESSED_EXTENSIONS
+        .iter()
+        .find_map(|(ext, _)| lower.strip_suffix(ext))
+        .unwrap_or(&lower);
+    base.ends_with(".csv") || base.ends_with(".tsv")
+}
+
+/// Where the CSV is written, possibly through a compressor.
+pub enum Output {
+    Plain(Box<dyn Write>),
+    Gzip(GzEncoder<Box<dyn Write>>),
+    Zstd(zstd::Encoder<'static, Box<dyn Write>>),
+}
+
+impl Output {
+    /// `-` stands for stdout. `level` defaults to the format's own default (6 for gzip, 3 for zstd).
+    pub fn create(path: &str, compression: OutputCompression, level: Option<u32>) -> Result<Self> {
+        let compression = match compression {
+            OutputCompression::Auto => COMPRESSED_EXTENSIONS
+                .iter()
+                .find(|(ext, _)| path.ends_with(ext))
+                .map_or(OutputCompression::None, |&(_, compression)| compression),
+            other => other,
+        };
+
+        // Validate the level before the output file gets truncated
+        let level = match compression {
+            OutputCompression::Gzip => level.unwrap_or(6),
+            OutputCompression::Zstd => level.unwrap_or(3),
+            _ => 0,
+        };
+        match compression {
+            OutputCompression::Gzip if level > 9 => {
+                bail!("gzip compression level must be between 0 and 9, got {}", level)
+            }
+            OutputCompression::Zstd if !(1..=22).contains(&level) => {
+                bail!("zstd compression level must be between 1 and 22, got {}", level)
+            }
+            _ => {}
+        }
+
+        let sink: Box<dyn Write> = match path {
+            "-" => Box::new(io::stdout().lock()),
+            _ => Box::new(File::create(path).with_context(|| format!("Cannot create output file: {}", path))?),
+        };
+
+        Ok(match compression {
+            OutputCompression::Auto | OutputCompression::None => Output::Plain(sink),
+            OutputCompression::Gzip => Output::Gzip(GzEncoder::new(sink, flate2::Compression::new(level))),
+            OutputCompression::Zstd => Output::Zstd(zstd::Encoder::new(sink, level as i32)?),
+        })
+    }
+
+    /// Write the compressor's trailer and flush everything to the sink.
+    pub fn finish(self) -> io::Result<()> {
+        let mut sink = match self {
+            Output::Plain(sink) => sink,
+            Output::Gzip(encoder) => encoder.finish()?,
+            Output::Zstd(encoder) => encoder.finish()?,
+        };
+        sink.flush()
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Plain(sink) => sink.write(buf),
+            Output::Gzip(encoder) => encoder.write(buf),
+            Output::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Plain(sink) => sink.flush(),
+            Output::Gzip(encoder) => encoder.flush(),
+            Output::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
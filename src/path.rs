@@ -0,0 +1,212 @@
+use anyhow::{bail, Result};
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    /// Object key; a numeric key also indexes arrays, as in JSON Pointer
+    Key(String),
+    Index(usize),
+    /// Every member of an object or item of an array
+    Wildcard,
+}
+
+/// A location inside a record, written as a JSON Pointer (`/user/address/city`),
+/// a JSONPath (`$.user.address.city`, `$.items[*].sku`) or a bare dotted path (`user.address.city`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+}
+
+impl JsonPath {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let segments = if spec.is_empty() {
+            Vec::new()
+        } else if let Some(pointer) = spec.strip_prefix('/') {
+            parse_pointer(pointer)
+        } else if let Some(path) = spec.strip_prefix('$') {
+            parse_jsonpath(spec, path)?
+        } else {
+            parse_jsonpath(spec, &format!(".{}", spec))?
+        };
+        Ok(Self { segments })
+    }
+
+    /// Whether the path can match more than one value.
+    pub fn has_wildcard(&self) -> bool {
+        self.segments.contains(&Segment::Wildcard)
+    }
+
+    /// Column name for the path: its segments joined with dots, e.g. `meta.ts`.
+    pub fn dotted(&self) -> String {
+        self.segments
+            .iter()
+            .map(|s| match s {
+                Segment::Key(k) => k.clone(),
+                Segment::Index(i) => i.to_string(),
+                Segment::Wildcard => "*".to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(".")
+    }
+
+    /// Every value the path points to, in document order.
+    pub fn select<'a>(&self, root: &'a Value) -> Vec<&'a Value> {
+        let mut current = vec![root];
+        for segment in &self.segments {
+            let mut next = Vec::new();
+            for v in current {
+                match (segment, v) {
+                    (Segment::Key(k), Value::Object(map)) => next.extend(map.get(k)),
+                    (Segment::Key(k), Value::Array(items)) => {
+                        next.extend(k.parse::<usize>().ok().and_then(|i| items.get(i)))
+                    }
+                    (Segment::Index(i), Value::Array(items)) => next.extend(items.get(*i)),
+                    (Segment::Wildcard, Value::Object(map)) => next.extend(map.values()),
+                    (Segment::Wildcard, Value::Array(items)) => next.extend(items.iter()),
+                    _ => {}
+                }
+            }
+            current = next;
+        }
+        current
+    }
+
+    /// Move the value the path points to out of `root`, leaving `null` in its place.
+    /// Wildcards match nothing here.
+    pub fn take(&self, root: &mut Value) -> Option<Value> {
+        let mut v = root;
+        for segment in &self.segments {
+            v = match (segment, v) {
+                (Segment::Key(k), Value::Object(map)) => map.get_mut(k)?,
+                (Segment::Key(k), Value::Array(items)) => items.get_mut(k.parse::<usize>().ok()?)?,
+                (Segment::Index(i), Value::Array(items)) => items.get_mut(*i)?,
+                _ => return None,
+            };
+        }
+        Some(v.take())
+    }
+}
+
+/// RFC 6901 pointer, without its leading `/`. A `*` segment is a wildcard.
+fn parse_pointer(pointer: &str) -> Vec<Segment> {
+    pointer
+        .split('/')
+        .map(|s| match s {
+            "*" => Segment::Wildcard,
+            _ => Segment::Key(s.replace("~1", "/").replace("~0", "~")),
+        })
+        .collect()
+}
+
+/// The subset of JSONPath made of `.name`, `.*`, `[N]`, `[*]` and `['name']` steps.
+fn parse_jsonpath(spec: &str, path: &str) -> Result<Vec<Segment>> {
+    let mut segments = Vec::new();
+    let mut rest = path;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('.') {
+            if after.starts_with('.') {
+                bail!("Invalid path \"{}\": recursive descent (`..`) is not supported", spec);
+            }
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            let name = &after[..end];
+            if name.is_empty() {
+                bail!("Invalid path \"{}\": empty key", spec);
+            }
+            segments.push(if name == "*" { Segment::Wildcard } else { Segment::Key(name.to_string()) });
+            rest = &after[end..];
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let Some(end) = after.find(']') else {
+                bail!("Invalid path \"{}\": missing `]`", spec);
+            };
+            let inner = after[..end].trim();
+            let quoted = inner
+                .strip_prefix('\'')
+                .and_then(|s| s.strip_suffix('\''))
+                .or_else(|| inner.strip_prefix('"').and_then(|s| s.strip_suffix('"')));
+            segments.push(match (inner, quoted) {
+                ("*", _) => Segment::Wildcard,
+                (_, Some(name)) => Segment::Key(name.to_string()),
+                _ => match inner.parse() {
+                    Ok(i) => Segment::Index(i),
+                    Err(_) => bail!("Invalid path \"{}\": unsupported selector [{}]", spec, inner),
+                },
+            });
+            rest = &after[end + 1..];
+        } else {
+            bail!("Invalid path \"{}\": expected `.` or `[` at \"{}\"", spec, rest);
+        }
+    }
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn segments(spec: &str) -> Vec<Segment> {
+        JsonPath::parse(spec).unwrap().segments
+    }
+
+    fn key(k: &str) -> Segment {
+        Segment::Key(k.to_string())
+    }
+
+    #[test]
+    fn parses_pointers() {
+        assert_eq!(segments("/user/address/city"), [key("user"), key("address"), key("city")]);
+        assert_eq!(segments("/a~1b/c~0d/~01"), [key("a/b"), key("c~d"), key("~1")]);
+        assert_eq!(segments("/items/*/sku"), [key("items"), Segment::Wildcard, key("sku")]);
+        assert_eq!(segments("/items/0"), [key("items"), key("0")]);
+        assert_eq!(segments(""), []);
+    }
+
+    #[test]
+    fn parses_jsonpath() {
+        assert_eq!(segments("$.user.city"), [key("user"), key("city")]);
+        assert_eq!(segments("$.items[2].sku"), [key("items"), Segment::Index(2), key("sku")]);
+        assert_eq!(segments("$['a.b'][\"c d\"]"), [key("a.b"), key("c d")]);
+        assert_eq!(segments("$.items[*].sku"), [key("items"), Segment::Wildcard, key("sku")]);
+        assert_eq!(segments("$.items.*"), [key("items"), Segment::Wildcard]);
+        assert_eq!(segments("$"), []);
+    }
+
+    #[test]
+    fn parses_dotted_paths() {
+        assert_eq!(segments("user.address.city"), [key("user"), key("address"), key("city")]);
+        assert_eq!(segments("items[0]"), [key("items"), Segment::Index(0)]);
+        assert_eq!(JsonPath::parse("meta.ts").unwrap().dotted(), "meta.ts");
+    }
+
+    #[test]
+    fn rejects_invalid_paths() {
+        for spec in ["$..a", "$.a[0", "$.a[x]", "$.a.", "a..b", "$a"] {
+            assert!(JsonPath::parse(spec).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn selects_values() {
+        let v = json!({"user": {"name": "Ann"}, "items": [{"sku": "a"}, {"sku": "b"}], "0": "zero"});
+        let select = |spec: &str| JsonPath::parse(spec).unwrap().select(&v).into_iter().cloned().collect::<Vec<_>>();
+        assert_eq!(select("/user/name"), [json!("Ann")]);
+        assert_eq!(select("$.items[1].sku"), [json!("b")]);
+        // A numeric key indexes arrays, and still matches object keys
+        assert_eq!(select("/items/0/sku"), [json!("a")]);
+        assert_eq!(select("/0"), [json!("zero")]);
+        assert_eq!(select("$.items[*].sku"), [json!("a"), json!("b")]);
+        assert_eq!(select("$.user.*"), [json!("Ann")]);
+        assert!(select("$.items[5]").is_empty());
+        assert!(select("/user/name/first").is_empty());
+    }
+
+    #[test]
+    fn takes_values() {
+        let mut v = json!({"data": {"a": 1}, "list": [10, 20]});
+        assert_eq!(JsonPath::parse("/data").unwrap().take(&mut v), Some(json!({"a": 1})));
+        assert_eq!(v["data"], Value::Null);
+        assert_eq!(JsonPath::parse("/list/1").unwrap().take(&mut v), Some(json!(20)));
+        assert_eq!(JsonPath::parse("/missing").unwrap().take(&mut v), None);
+        assert_eq!(JsonPath::parse("/list/*").unwrap().take(&mut v), None);
+    }
+}
@@ -0,0 +1,213 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use anyhow::{bail, Context, Result};
+use base64::prelude::{BASE64_STANDARD, Engine};
+use serde_json::json;
+
+use crate::diagnostic::ParseError;
+use crate::input::Position;
+
+/// Why a record could not be converted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectKind {
+    /// The line is not valid JSON
+    Parse,
+    /// The line (or its root) is valid JSON but not an object
+    NotObject,
+    /// The record could not be reshaped, e.g. a missing root or an oversized array
+    Convert,
+    /// The record does not fit the CSV header, e.g. with `--on-new-key error`
+    Schema,
+    /// A value does not have the type set with `--cast`
+    Cast,
+}
+
+impl RejectKind {
+    const ALL: [RejectKind; 5] = [
+        RejectKind::Parse,
+        RejectKind::NotObject,
+        RejectKind::Convert,
+        RejectKind::Schema,
+        RejectKind::Cast,
+    ];
+
+    /// Identifier used in the rejects file.
+    fn name(self) -> &'static str {
+        match self {
+            RejectKind::Parse => "parse",
+            RejectKind::NotObject => "not_object",
+            RejectKind::Convert => "convert",
+            RejectKind::Schema => "schema",
+            RejectKind::Cast => "cast",
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            RejectKind::Parse => "JSON parse error",
+            RejectKind::NotObject => "not a JSON object",
+            RejectKind::Convert => "conversion error",
+            RejectKind::Schema => "schema violation",
+            RejectKind::Cast => "cast error",
+        }
+    }
+}
+
+/// A record-level failure. Fatal by default, skipped with `--skip-invalid`.
+#[derive(Debug)]
+pub struct Rejection {
+    pub kind: RejectKind,
+    pub pos: Position,
+    /// 1-based byte column of the error within the line, when known
+    pub column: Option<usize>,
+    /// The rejected line as read, without its terminator
+    pub raw: Vec<u8>,
+    pub cause: anyhow::Error,
+}
+
+impl Rejection {
+    pub fn new(kind: RejectKind, pos: &Position, raw: &[u8], cause: impl Into<anyhow::Error>) -> Self {
+        Self {
+            kind,
+            pos: pos.clone(),
+            column: None,
+            raw: raw.to_vec(),
+            cause: cause.into(),
+        }
+    }
+
+    pub fn parse(pos: &Position, raw: &[u8], err: ParseError) -> Self {
+        Self {
+            kind: RejectKind::Parse,
+            pos: pos.clone(),
+            column: Some(err.column),
+            raw: raw.to_vec(),
+            cause: err.into(),
+        }
+    }
+
+    /// Byte offset of the error in the (decompressed) file.
+    pub fn offset(&self) -> u64 {
+        self.pos.offset + self.column.map_or(0, |c| c as u64 - 1)
+    }
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} on {}", self.kind.describe(), self.pos)?;
+        if let Some(column) = self.column {
+            write!(f, ", column {}", column)?;
+        }
+        write!(f, " (byte offset {})", self.offset())
+    }
+}
+
+impl std::error::Error for Rejection {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.cause.as_ref())
+    }
+}
+
+/// Decides whether a failed record aborts the run, and counts the skipped ones.
+pub struct Rejects {
+    skip: bool,
+    /// Kinds that are skipped even without `skip`
+    always_skip: Vec<RejectKind>,
+    max_errors: Option<u64>,
+    /// Report every skipped record on stderr
+    verbose: bool,
+    counts: [u64; RejectKind::ALL.len()],
+    /// Dead-letter file receiving the skipped lines
+    file: Option<BufWriter<File>>,
+}
+
+impl Rejects {
+    pub fn new(skip: bool, max_errors: Option<u64>, verbose: bool) -> Self {
+        Self {
+            skip,
+            always_skip: Vec::new(),
+            max_errors,
+            verbose,
+            counts: [0; RejectKind::ALL.len()],
+            file: None,
+        }
+    }
+
+    /// Skip records rejected for `kind` whether or not skipping is enabled.
+    pub fn always_skip(&mut self, kind: RejectKind) {
+        self.always_skip.push(kind);
+    }
+
+    /// Write every skipped line to a JSONL file at `path`.
+    pub fn write_to(&mut self, path: &str) -> Result<()> {
+        let file = File::create(path).with_context(|| format!("Cannot create rejects file: {}", path))?;
+        self.file = Some(BufWriter::new(file));
+        Ok(())
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Skip the record behind `err` when it is a rejection and skipping is enabled,
+    /// otherwise hand the error back to abort the run.
+    pub fn handle(&mut self, err: anyhow::Error) -> Result<()> {
+        let rejection = match err.downcast_ref::<Rejection>() {
+            Some(rejection) if self.skip || self.always_skip.contains(&rejection.kind) => rejection,
+            _ => return Err(err),
+        };
+
+        self.counts[rejection.kind as usize] += 1;
+        if self.verbose {
+            eprintln!("warning: skipped {}: {:#}", rejection, rejection.cause);
+        }
+        if let Some(file) = &mut self.file {
+            let mut entry = json!({
+                "file": &*rejection.pos.file,
+                "line": rejection.pos.line,
+                "offset": rejection.pos.offset,
+                "column": rejection.column,
+                "kind": rejection.kind.name(),
+                "error": match rejection.cause.downcast_ref::<ParseError>() {
+                    Some(err) => err.summary(),
+                    None => format!("{:#}", rejection.cause),
+                },
+                "raw": String::from_utf8_lossy(&rejection.raw),
+            });
+            // `raw` cannot hold bytes that are not UTF-8, keep the exact line next to it
+            if std::str::from_utf8(&rejection.raw).is_err() {
+                entry["raw_base64"] = BASE64_STANDARD.encode(&rejection.raw).into();
+            }
+            writeln!(file, "{}", entry).context("Cannot write to the rejects file")?;
+        }
+        if let Some(max) = self.max_errors
+            && self.total() > max
+        {
+            bail!("Too many invalid records, giving up after {} (--max-errors {})", self.total(), max);
+        }
+        Ok(())
+    }
+
+    /// Flush the rejects file, if any.
+    pub fn finish(&mut self) -> Result<()> {
+        if let Some(file) = &mut self.file {
+            file.flush().context("Cannot write to the rejects file")?;
+        }
+        Ok(())
+    }
+
+    /// One-line account of the skipped records, if any.
+    pub fn summary(&self) -> Option<String> {
+        if self.total() == 0 {
+            return None;
+        }
+        let details: Vec<String> = RejectKind::ALL
+            .iter()
+            .zip(self.counts)
+            .filter(|(_, n)| *n > 0)
+            .map(|(kind, n)| format!("{}: {}", kind.describe(), n))
+            .collect();
+        Some(format!("Skipped {} invalid record(s) ({})", self.total(), details.join(", ")))
+    }
+}
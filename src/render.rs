@@ -0,0 +1,78 @@
+use clap::ValueEnum;
+use serde_json::Value;
+
+use crate::number::NumberFormat;
+
+/// How booleans are written.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BoolFormat {
+    /// `true` / `false`
+    #[value(name = "true-false")]
+    TrueFalse,
+    /// `1` / `0`
+    #[value(name = "1-0")]
+    OneZero,
+    /// `TRUE` / `FALSE`
+    #[value(name = "TRUE-FALSE")]
+    Upper,
+    /// `yes` / `no`
+    #[value(name = "yes-no")]
+    YesNo,
+}
+
+impl BoolFormat {
+    fn render(self, b: bool) -> &'static str {
+        match (self, b) {
+            (BoolFormat::TrueFalse, true) => "true",
+            (BoolFormat::TrueFalse, false) => "false",
+            (BoolFormat::OneZero, true) => "1",
+            (BoolFormat::OneZero, false) => "0",
+            (BoolFormat::Upper, true) => "TRUE",
+            (BoolFormat::Upper, false) => "FALSE",
+            (BoolFormat::YesNo, true) => "yes",
+            (BoolFormat::YesNo, false) => "no",
+        }
+    }
+}
+
+/// Turns JSON values into CSV cells.
+#[derive(Clone)]
+pub struct Renderer {
+    /// Join arrays of scalars with this delimiter instead of writing them as JSON
+    pub array_join: Option<String>,
+    /// Cell text for `null`
+    pub null_token: String,
+    /// Cell text for a column the record does not have
+    pub missing_token: String,
+    pub bools: BoolFormat,
+    pub numbers: NumberFormat,
+}
+
+impl Renderer {
+    pub fn render(&self, v: &Value) -> String {
+        match v {
+            Value::Null => self.null_token.clone(),
+            Value::Bool(b) => self.bools.render(*b).to_string(),
+            Value::Number(n) => self.numbers.format(&n.to_string()),
+            Value::String(s) => s.clone(),
+            Value::Array(items) => match &self.array_join {
+                Some(sep) if items.iter().all(is_scalar) => {
+                    items.iter().map(|item| self.render(item)).collect::<Vec<_>>().join(sep)
+                }
+                // Arrays holding objects or arrays fall back to compact JSON
+                _ => v.to_string(),
+            },
+            // For objects, fall back to compact JSON representation
+            Value::Object(_) => v.to_string(),
+        }
+    }
+
+    /// Cell for a column, which may be absent from the record.
+    pub fn render_field(&self, v: Option<&Value>) -> String {
+        v.map(|v| self.render(v)).unwrap_or_else(|| self.missing_token.clone())
+    }
+}
+
+fn is_scalar(v: &Value) -> bool {
+    !matches!(v, Value::Array(_) | Value::Object(_))
+}
@@ -0,0 +1,82 @@
+use anyhow::{bail, Result};
+use serde_json::{Map, Value};
+
+use crate::path::JsonPath;
+use crate::toplevel::describe;
+
+/// Parse a `--root` path, which has to point at a single value.
+pub fn parse_root(spec: &str) -> Result<JsonPath> {
+    let path = JsonPath::parse(spec)?;
+    if path.has_wildcard() {
+        bail!("Invalid root \"{}\": wildcards are not supported, the root must be a single value", spec);
+    }
+    Ok(path)
+}
+
+/// An envelope field copied into every row: `PATH` (column named after the dotted path) or `NAME=PATH`.
+#[derive(Debug, Clone)]
+pub struct EnvelopeField {
+    name: String,
+    path: JsonPath,
+}
+
+impl EnvelopeField {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (name, path) = match spec.split_once('=') {
+            Some((name, path)) => (Some(name.trim()), JsonPath::parse(path.trim())?),
+            None => (None, JsonPath::parse(spec.trim())?),
+        };
+        Ok(Self {
+            name: name.map_or_else(|| path.dotted(), String::from),
+            path,
+        })
+    }
+}
+
+/// Descends into wrapped payloads, e.g. `_source` of Elasticsearch hits or `data` of an envelope.
+pub struct RootSelector {
+    pub root: JsonPath,
+    pub envelope: Vec<EnvelopeField>,
+}
+
+impl RootSelector {
+    pub fn select(&self, mut value: Value) -> Result<Value> {
+        // Read the envelope fields before the root is moved out of the record
+        let merged: Vec<(String, Value)> = self
+            .envelope
+            .iter()
+            .filter_map(|field| field.path.select(&value).first().map(|v| (field.name.clone(), (*v).clone())))
+            .collect();
+
+        let Some(mut root) = self.root.take(&mut value) else {
+            bail!("record has no value at the root path \"{}\"", self.root.dotted());
+        };
+        if !merged.is_empty() {
+            // An array root holds the records themselves, as with `--on-array records`
+            match &mut root {
+                Value::Object(map) => merge(map, &merged)?,
+                Value::Array(items) => {
+                    for item in items {
+                        match item {
+                            Value::Object(map) => merge(map, &merged)?,
+                            other => bail!("cannot add envelope fields to {} in the root array", describe(other)),
+                        }
+                    }
+                }
+                other => bail!("cannot add envelope fields to {} at the root path", describe(other)),
+            }
+        }
+        Ok(root)
+    }
+}
+
+/// Add the envelope fields to a root object, refusing to overwrite its own fields.
+fn merge(map: &mut Map<String, Value>, merged: &[(String, Value)]) -> Result<()> {
+    for (name, v) in merged {
+        if map.contains_key(name) {
+            bail!("envelope field \"{}\" clashes with a field of the root object", name);
+        }
+        map.insert(name.clone(), v.clone());
+    }
+    Ok(())
+}
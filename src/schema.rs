@@ -0,0 +1,192 @@
+use clap::ValueEnum;
+use indexmap::IndexMap;
+use serde_json::{Map, Value, json};
+
+/// Order of the discovered columns in the CSV header.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColumnOrder {
+    /// Order of first appearance in the input
+    FirstSeen,
+    /// Alphabetical order of the keys
+    Alphabetical,
+    /// Most frequent keys first, ties keep the order of first appearance
+    Frequency,
+}
+
+/// Union of the keys seen across records, with the number of records each key appeared in.
+#[derive(Default)]
+pub struct KeyUnion {
+    counts: IndexMap<String, u64>,
+}
+
+impl KeyUnion {
+    /// Add the keys of `obj`, remembering the order in which they first show up.
+    pub fn add(&mut self, obj: &Map<String, Value>) {
+        for k in obj.keys() {
+            match self.counts.get_mut(k) {
+                Some(n) => *n += 1,
+                None => {
+                    self.counts.insert(k.clone(), 1);
+                }
+            }
+        }
+    }
+
+    /// Turn the union into header columns in the requested order.
+    pub fn into_headers(self, order: ColumnOrder) -> Vec<String> {
+        let mut keys: Vec<(String, u64)> = self.counts.into_iter().collect();
+        match order {
+            ColumnOrder::FirstSeen => {}
+            ColumnOrder::Alphabetical => keys.sort_by(|a, b| a.0.cmp(&b.0)),
+            // Stable sort, so equally frequent keys stay in first-seen order
+            ColumnOrder::Frequency => keys.sort_by_key(|k| std::cmp::Reverse(k.1)),
+        }
+        keys.into_iter().map(|(k, _)| k).collect()
+    }
+}
+
+/// Output format of the `schema` report.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// Aligned text table
+    Table,
+    /// JSON document
+    Json,
+}
+
+/// JSON types told apart by the report; numbers are split into integers and floats.
+const TYPES: [&str; 7] = ["null", "bool", "int", "float", "string", "object", "array"];
+
+fn type_index(v: &Value) -> usize {
+    match v {
+        Value::Null => 0,
+        Value::Bool(_) => 1,
+        Value::Number(n) if !n.to_string().contains(['.', 'e', 'E']) => 2,
+        Value::Number(_) => 3,
+        Value::String(_) => 4,
+        Value::Object(_) => 5,
+        Value::Array(_) => 6,
+    }
+}
+
+/// What was observed for one key.
+#[derive(Default)]
+struct KeyProfile {
+    /// Number of values of each of `TYPES`
+    types: [u64; TYPES.len()],
+    /// Length in characters of the longest string
+    max_len: Option<usize>,
+    examples: Vec<Value>,
+}
+
+/// Observed types, nullability and example values of every key, in order of first appearance.
+pub struct Profile {
+    records: u64,
+    keys: IndexMap<String, KeyProfile>,
+    max_examples: usize,
+}
+
+impl Profile {
+    pub fn new(max_examples: usize) -> Self {
+        Self {
+            records: 0,
+            keys: IndexMap::new(),
+            max_examples,
+        }
+    }
+
+    pub fn add(&mut self, obj: &Map<String, Value>) {
+        self.records += 1;
+        for (k, v) in obj {
+            let key = self.keys.entry(k.clone()).or_default();
+            key.types[type_index(v)] += 1;
+            if let Value::String(s) = v {
+                key.max_len = key.max_len.max(Some(s.chars().count()));
+            }
+            if !v.is_null() && key.examples.len() < self.max_examples && !key.examples.contains(v) {
+                key.examples.push(v.clone());
+            }
+        }
+    }
+
+    fn types(key: &KeyProfile) -> Vec<&'static str> {
+        TYPES.iter().zip(key.types).filter(|(_, n)| *n > 0).map(|(t, _)| *t).collect()
+    }
+
+    /// Whether the key was null or missing in some record.
+    fn nullable(&self, key: &KeyProfile) -> bool {
+        key.types[0] > 0 || self.missing(key) > 0
+    }
+
+    /// Records that do not have the key.
+    fn missing(&self, key: &KeyProfile) -> u64 {
+        self.records - key.types.iter().sum::<u64>()
+    }
+
+    pub fn to_json(&self) -> Value {
+        let columns: Vec<Value> = self
+            .keys
+            .iter()
+            .map(|(name, key)| {
+                let types: Map<String, Value> = TYPES
+                    .iter()
+                    .zip(key.types)
+                    .filter(|(_, n)| *n > 0)
+                    .map(|(t, n)| (t.to_string(), n.into()))
+                    .collect();
+                json!({
+                    "name": name,
+                    "types": types,
+                    "nullable": self.nullable(key),
+                    "nulls": key.types[0],
+                    "missing": self.missing(key),
+                    "max_length": key.max_len,
+                    "examples": key.examples,
+                })
+            })
+            .collect();
+        json!({ "records": self.records, "columns": columns })
+    }
+
+    /// The report as a text table, one row per key.
+    pub fn table(&self) -> String {
+        let mut rows = vec![["COLUMN", "TYPES", "NULLABLE", "NULLS", "MISSING", "MAX_LEN", "EXAMPLES"].map(String::from)];
+        for (name, key) in &self.keys {
+            let examples: Vec<String> = key.examples.iter().map(|v| truncate(&v.to_string(), 30)).collect();
+            rows.push([
+                name.clone(),
+                Self::types(key).join("|"),
+                if self.nullable(key) { "yes" } else { "no" }.to_string(),
+                key.types[0].to_string(),
+                self.missing(key).to_string(),
+                key.max_len.map(|n| n.to_string()).unwrap_or_default(),
+                examples.join(", "),
+            ]);
+        }
+
+        let mut widths = [0; 7];
+        for row in &rows {
+            for (w, cell) in widths.iter_mut().zip(row) {
+                *w = (*w).max(cell.chars().count());
+            }
+        }
+        let mut out = String::new();
+        for row in &rows {
+            let cells: Vec<String> = row.iter().zip(widths).map(|(cell, w)| format!("{:<w$}", cell)).collect();
+            out.push_str(cells.join("  ").trim_end());
+            out.push('\n');
+        }
+        out.push_str(&format!("{} record(s), {} column(s)\n", self.records, self.keys.len()));
+        out
+    }
+}
+
+/// Shorten `s` to at most `max` characters, marking the cut with `…`.
+fn truncate(s: &str, max: usize) -> String {
+    if s.chars().count() <= max {
+        return s.to_string();
+    }
+    let mut short: String = s.chars().take(max - 1).collect();
+    short.push('…');
+    short
+}
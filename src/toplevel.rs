@@ -0,0 +1,77 @@
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+
+/// What to do with lines holding a string, number, boolean or null.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScalarPolicy {
+    /// Treat the line as invalid
+    Reject,
+    /// Write the value in a single column (see `--value-column`)
+    Wrap,
+}
+
+/// What to do with lines holding an array.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArrayPolicy {
+    /// Treat the line as invalid
+    Reject,
+    /// One row per line, items in positional columns `col_0`, `col_1`, ...
+    Positional,
+    /// One record per item
+    Records,
+}
+
+/// Turns any top-level JSON value into the records it stands for.
+pub struct TopLevel {
+    pub scalars: ScalarPolicy,
+    pub arrays: ArrayPolicy,
+    pub value_column: String,
+    pub positional_prefix: String,
+}
+
+impl TopLevel {
+    pub fn records(&self, value: Value) -> Result<Vec<Map<String, Value>>> {
+        match value {
+            Value::Object(map) => Ok(vec![map]),
+            Value::Array(items) => match self.arrays {
+                ArrayPolicy::Reject => Err(anyhow!("found an array")),
+                ArrayPolicy::Positional => {
+                    let row = items
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, v)| (format!("{}{}", self.positional_prefix, i), v))
+                        .collect();
+                    Ok(vec![row])
+                }
+                ArrayPolicy::Records => {
+                    let mut records = Vec::new();
+                    for item in items {
+                        records.extend(self.records(item)?);
+                    }
+                    Ok(records)
+                }
+            },
+            scalar => match self.scalars {
+                ScalarPolicy::Reject => Err(anyhow!("found {}", describe(&scalar))),
+                ScalarPolicy::Wrap => {
+                    let mut row = Map::new();
+                    row.insert(self.value_column.clone(), scalar);
+                    Ok(vec![row])
+                }
+            },
+        }
+    }
+}
+
+/// The kind of a JSON value, for messages.
+pub fn describe(v: &Value) -> &'static str {
+    match v {
+        Value::String(_) => "a string",
+        Value::Number(_) => "a number",
+        Value::Bool(_) => "a boolean",
+        Value::Null => "null",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}